@@ -0,0 +1,276 @@
+use crate::types::{
+    AudioLevels, BoundsType, Crop, EncoderType, ModuleStatus, MonitoringType, OrderMovement,
+    OutputStatus, ReplaySaved, StreamDestination, Vec2,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use tonic::Status;
+
+#[cfg(feature = "libobs")]
+mod hl;
+mod mock;
+
+#[cfg(feature = "libobs")]
+pub use self::hl::HlBackend;
+pub use mock::MockBackend;
+
+/// A source as reported by `GetSourceStatus`.
+#[derive(Clone, Debug)]
+pub struct SourceInfo {
+    pub name: String,
+    pub has_video: bool,
+    pub has_audio: bool,
+    pub is_composite: bool,
+    pub volume: f32,
+    pub muted: bool,
+    pub balance: f32,
+    pub sync_offset_ns: i64,
+    pub mixers: u32,
+    pub monitoring_type: MonitoringType,
+}
+
+/// A stream output as reported by `GetStreamStatus`.
+#[derive(Clone, Debug)]
+pub struct OutputInfo {
+    pub name: String,
+    pub destination: StreamDestination,
+    pub status: OutputStatus,
+}
+
+/// A scene item as reported by `ListSceneItems`.
+#[derive(Clone, Debug)]
+pub struct SceneItemInfo {
+    pub id: i64,
+    pub source: String,
+    pub visible: bool,
+    pub position: Vec2,
+    pub scale: Vec2,
+    /// In degrees.
+    pub rotation: f32,
+    pub bounds: Bounds,
+    pub crop: Crop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub bounds_type: BoundsType,
+    pub size: Vec2,
+    pub alignment: u32,
+}
+
+/// Changes to a scene item's transform.  Fields left at None are kept.
+#[derive(Clone, Debug, Default)]
+pub struct Transform {
+    pub position: Option<Vec2>,
+    pub scale: Option<Vec2>,
+    pub rotation: Option<f32>,
+    pub bounds_type: Option<BoundsType>,
+    /// Only applied together with `bounds_type`.
+    pub bounds_size: Option<Vec2>,
+    /// Only applied together with `bounds_type`.
+    pub bounds_alignment: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ItemOrder {
+    Move(OrderMovement),
+    /// Index from the bottom.
+    Position(i32),
+}
+
+/// A filter as reported by `ListFilters`.
+#[derive(Clone, Debug)]
+pub struct FilterInfo {
+    pub name: String,
+    pub kind: String,
+    pub enabled: bool,
+    pub settings_json: String,
+}
+
+/// The program scene and the transition used to switch to another one.
+#[derive(Clone, Debug)]
+pub struct CurrentScene {
+    pub scene: String,
+    pub transition: String,
+    pub duration_ms: u32,
+    /// Every transition that can be switched with.
+    pub transitions: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordingInfo {
+    pub status: OutputStatus,
+    /// The file being written, or the last one written when stopped.
+    pub path: Option<PathBuf>,
+}
+
+/// Audio levels of a source, collected between polls.
+pub trait LevelMeter: Send {
+    fn name(&self) -> &str;
+    /// Returns the levels seen since the last call, or `None` if the source
+    /// has not produced any audio.
+    fn take(&self) -> Option<AudioLevels>;
+}
+
+// An entry in a vlc_source's `playlist` setting.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct PlaylistItem {
+    hidden: bool,
+    selected: bool,
+    value: String,
+}
+
+impl PlaylistItem {
+    pub(crate) fn new(url: &str) -> PlaylistItem {
+        PlaylistItem {
+            hidden: false,
+            selected: false,
+            value: url.into(),
+        }
+    }
+}
+
+/// Parses the settings json of a request.  An empty string means no
+/// settings.  Anything but an object is rejected, libobs would read it as
+/// empty settings and a reset would wipe them.
+pub fn settings_from_json(json: &str) -> Result<Option<Map<String, Value>>, Status> {
+    if json.is_empty() {
+        return Ok(None);
+    }
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| Status::invalid_argument(format!("invalid settings json: {}", e)))?;
+    match value {
+        Value::Object(settings) => Ok(Some(settings)),
+        _ => Err(Status::invalid_argument(format!(
+            "settings json must be an object, got {}",
+            json
+        ))),
+    }
+}
+
+/// The obs operations behind the gRPC server.  Backends are created on and
+/// only used from the worker thread, so they don't need to be `Send`.
+///
+/// Errors are returned as the `Status` the client gets, so that every
+/// backend fails the same way, e.g. `not_found` for unknown sources or
+/// outputs.  Requests are validated by the server before they get here.
+pub trait ObsBackend: 'static {
+    fn sources(&self) -> Vec<SourceInfo>;
+    /// Creates a source of type `kind` and adds it to `scene` unless that is
+    /// empty.  Returns the id of the new scene item, zero without a scene.
+    fn create_source(
+        &mut self,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+        scene: &str,
+    ) -> Result<i64, Status>;
+    fn remove_source(&mut self, name: &str) -> Result<(), Status>;
+    fn source_settings(&self, name: &str, pretty: bool) -> Result<String, Status>;
+    /// Merges `json` into the source's settings, or replaces them unless
+    /// `merge` is set.
+    fn set_source_settings(&mut self, name: &str, json: &str, merge: bool) -> Result<(), Status>;
+    /// Replaces the playlist of a media source.
+    fn set_playlist(&mut self, source: &str, urls: &[String]) -> Result<(), Status>;
+
+    fn set_volume(&mut self, source: &str, volume: f32) -> Result<(), Status>;
+    fn set_muted(&mut self, source: &str, muted: bool) -> Result<(), Status>;
+    fn set_balance(&mut self, source: &str, balance: f32) -> Result<(), Status>;
+    fn set_sync_offset(&mut self, source: &str, offset_ns: i64) -> Result<(), Status>;
+    fn set_mixers(&mut self, source: &str, mixers: u32) -> Result<(), Status>;
+    fn set_monitoring_type(
+        &mut self,
+        source: &str,
+        monitoring_type: MonitoringType,
+    ) -> Result<(), Status>;
+    /// Starts metering `sources`, or every source with audio if empty.  The
+    /// meters are dropped on the worker thread.
+    fn watch_levels(&mut self, sources: &[String]) -> Result<Vec<Box<dyn LevelMeter>>, Status>;
+
+    /// The items of `scene`, bottom first.
+    fn scene_items(&self, scene: &str) -> Result<Vec<SceneItemInfo>, Status>;
+    /// Items are found by the name of their source.
+    fn set_item_transform(
+        &mut self,
+        scene: &str,
+        item: &str,
+        transform: &Transform,
+    ) -> Result<(), Status>;
+    fn set_item_crop(&mut self, scene: &str, item: &str, crop: Crop) -> Result<(), Status>;
+    fn set_item_visible(&mut self, scene: &str, item: &str, visible: bool) -> Result<(), Status>;
+    fn set_item_order(&mut self, scene: &str, item: &str, order: ItemOrder) -> Result<(), Status>;
+
+    fn filters(&self, source: &str) -> Result<Vec<FilterInfo>, Status>;
+    fn add_filter(
+        &mut self,
+        source: &str,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+    ) -> Result<(), Status>;
+    fn remove_filter(&mut self, source: &str, filter: &str) -> Result<(), Status>;
+    fn set_filter_enabled(
+        &mut self,
+        source: &str,
+        filter: &str,
+        enabled: bool,
+    ) -> Result<(), Status>;
+    fn move_filter(
+        &mut self,
+        source: &str,
+        filter: &str,
+        movement: OrderMovement,
+    ) -> Result<(), Status>;
+    /// Like `set_source_settings`, for a filter.
+    fn set_filter_settings(
+        &mut self,
+        source: &str,
+        filter: &str,
+        json: &str,
+        merge: bool,
+    ) -> Result<(), Status>;
+
+    /// Switches the program to `scene`.  The current transition and its
+    /// duration are used unless given.
+    fn set_current_scene(
+        &mut self,
+        scene: &str,
+        transition: Option<&str>,
+        duration_ms: Option<u32>,
+    ) -> Result<(), Status>;
+    fn current_scene(&self) -> Result<CurrentScene, Status>;
+
+    fn modules(&self) -> Vec<ModuleStatus>;
+    fn encoders(&self) -> Result<Vec<EncoderType>, Status>;
+
+    /// Starts recording to a new file named after `name` and returns its
+    /// path.
+    fn start_recording(&mut self, name: &str) -> Result<PathBuf, Status>;
+    fn stop_recording(&mut self) -> Result<(), Status>;
+    fn recording(&self) -> RecordingInfo;
+
+    fn start_replay_buffer(&mut self) -> Result<(), Status>;
+    fn stop_replay_buffer(&mut self) -> Result<(), Status>;
+    fn replay_buffer_status(&self) -> OutputStatus;
+    /// Starts saving the buffer.  The receiver gets the path once the file
+    /// is complete, or an error if the buffer stops first.  Fails with
+    /// `failed_precondition` while another replay is being saved.
+    fn save_replay(&mut self) -> Result<ReplaySaved, Status>;
+
+    fn outputs(&self) -> Vec<OutputInfo>;
+    fn output(&self, name: &str) -> Result<OutputInfo, Status>;
+    /// Adds a stopped output.
+    fn add_output(&mut self, name: &str, destination: StreamDestination) -> Result<(), Status>;
+    /// Removes an output, stopping it if it is active.
+    fn remove_output(&mut self, name: &str) -> Result<(), Status>;
+    fn start_output(&mut self, name: &str) -> Result<(), Status>;
+    fn stop_output(&mut self, name: &str) -> Result<(), Status>;
+    /// Changes where an output streams to.  An active output is restarted,
+    /// which may only happen after this returns.
+    fn set_output_destination(
+        &mut self,
+        name: &str,
+        destination: StreamDestination,
+    ) -> Result<(), Status>;
+}
@@ -0,0 +1,583 @@
+use super::{
+    settings_from_json, Bounds, CurrentScene, FilterInfo, ItemOrder, LevelMeter, ObsBackend,
+    OutputInfo, PlaylistItem, RecordingInfo, SceneItemInfo, SourceInfo, Transform,
+};
+use crate::{
+    hl::{
+        self, Crop, Data, EncoderType, Filter, ModuleStatus, MonitoringType, OrderMovement,
+        OutputStatus, ReplaySaved, Scene, SceneItem, Session, Source, SourceEvents,
+        StreamDestination, StreamOutput,
+    },
+    levels::LevelWatch,
+    reconnect::{self, OutputEvents},
+};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use tonic::Status;
+
+/// The backend the service runs with, on top of a libobs session.
+pub struct HlBackend {
+    // Keeps the sources created by `create_source` alive until they are
+    // removed.  Declared first so they are released before obs shuts down.
+    sources: Vec<Source>,
+    pub session: Session,
+    output_events: OutputEvents,
+    // Kept alive so source signals keep reaching the event feed.
+    _source_events: SourceEvents,
+}
+
+impl HlBackend {
+    pub fn new(
+        session: Session,
+        source_events: SourceEvents,
+        output_events: OutputEvents,
+    ) -> HlBackend {
+        HlBackend {
+            sources: Vec::new(),
+            session,
+            output_events,
+            _source_events: source_events,
+        }
+    }
+
+    fn output_mut(&mut self, name: &str) -> Result<&mut StreamOutput, Status> {
+        self.session
+            .output_mut(name)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+}
+
+fn find_source(name: &str) -> Result<Source, Status> {
+    Source::by_name(name).map_err(|e| Status::not_found(e.to_string()))
+}
+
+fn find_scene(scene: &str) -> Result<Scene, Status> {
+    Scene::by_name(scene).map_err(|e| Status::not_found(e.to_string()))
+}
+
+fn find_scene_item(scene: &str, item: &str) -> Result<SceneItem, Status> {
+    find_scene(scene)?
+        .find_item(item)
+        .map_err(|e| Status::not_found(e.to_string()))
+}
+
+fn find_filter(source: &str, filter: &str) -> Result<Filter, Status> {
+    find_source(source)?
+        .get_filter(filter)
+        .map_err(|e| Status::not_found(e.to_string()))
+}
+
+// Settings json is parsed once by `settings_from_json` and handed to libobs
+// as obs_data.
+fn to_data(settings: Map<String, Value>) -> Result<Data, Status> {
+    Data::from_json(&Value::Object(settings).to_string())
+        .map_err(|e| Status::internal(e.to_string()))
+}
+
+fn settings_data(json: &str) -> Result<Option<Data>, Status> {
+    settings_from_json(json)?.map(to_data).transpose()
+}
+
+fn required_settings_data(json: &str) -> Result<Data, Status> {
+    settings_data(json)?.ok_or_else(|| Status::invalid_argument("settings json is required"))
+}
+
+fn output_info(output: &StreamOutput) -> OutputInfo {
+    OutputInfo {
+        name: output.get_name().into(),
+        destination: output.destination().clone(),
+        status: output.status(),
+    }
+}
+
+fn scene_item_info(item: &SceneItem) -> SceneItemInfo {
+    SceneItemInfo {
+        id: item.get_id(),
+        source: item.get_source().get_name().unwrap_or("".into()),
+        visible: item.is_visible(),
+        position: item.get_pos(),
+        scale: item.get_scale(),
+        rotation: item.get_rot(),
+        bounds: Bounds {
+            bounds_type: item.get_bounds_type(),
+            size: item.get_bounds(),
+            alignment: item.get_bounds_alignment(),
+        },
+        crop: item.get_crop(),
+    }
+}
+
+fn filter_info(filter: &Filter) -> Result<FilterInfo, Status> {
+    let info = || -> anyhow::Result<FilterInfo> {
+        Ok(FilterInfo {
+            name: filter.get_name()?,
+            kind: filter.get_kind()?,
+            enabled: filter.is_enabled(),
+            settings_json: filter.as_source().get_settings()?.to_json()?,
+        })
+    };
+    info().map_err(|e| Status::internal(e.to_string()))
+}
+
+impl ObsBackend for HlBackend {
+    fn sources(&self) -> Vec<SourceInfo> {
+        let mut sources = Vec::new();
+        Source::for_each(|source: &Source| {
+            sources.push(SourceInfo {
+                name: source.get_name().unwrap_or("".into()),
+                has_video: source.has_video(),
+                has_audio: source.has_audio(),
+                is_composite: source.is_composite(),
+                volume: source.get_volume(),
+                muted: source.is_muted(),
+                balance: source.get_balance(),
+                sync_offset_ns: source.get_sync_offset(),
+                mixers: source.get_audio_mixers(),
+                monitoring_type: source.get_monitoring_type(),
+            });
+        });
+        sources
+    }
+
+    fn create_source(
+        &mut self,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+        scene: &str,
+    ) -> Result<i64, Status> {
+        if Source::by_name(name).is_ok() {
+            return Err(Status::already_exists(format!(
+                "source {:?} already exists",
+                name
+            )));
+        }
+        if !Source::type_exists(kind).map_err(|e| Status::invalid_argument(e.to_string()))? {
+            return Err(Status::invalid_argument(format!(
+                "unknown source type {:?}",
+                kind
+            )));
+        }
+        let mut settings = match settings {
+            Some(json) => settings_data(json)?,
+            None => None,
+        };
+        let mut scene = if scene.is_empty() {
+            None
+        } else {
+            Some(find_scene(scene)?)
+        };
+
+        let source = Source::create(kind, name, settings.as_mut())
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        match scene.as_mut() {
+            Some(scene) => match scene.add(&source) {
+                Ok(item) => {
+                    self.sources.push(source);
+                    Ok(item.get_id())
+                }
+                Err(e) => {
+                    source.remove();
+                    Err(Status::internal(e.to_string()))
+                }
+            },
+            None => {
+                self.sources.push(source);
+                Ok(0)
+            }
+        }
+    }
+
+    fn remove_source(&mut self, name: &str) -> Result<(), Status> {
+        let source = find_source(name)?;
+        self.sources.retain(|kept| *kept != source);
+        source.remove();
+        Ok(())
+    }
+
+    fn source_settings(&self, name: &str, pretty: bool) -> Result<String, Status> {
+        let settings = find_source(name)?
+            .get_settings()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if pretty {
+            settings.to_json_pretty()
+        } else {
+            settings.to_json()
+        }
+        .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn set_source_settings(&mut self, name: &str, json: &str, merge: bool) -> Result<(), Status> {
+        let mut source = find_source(name)?;
+        let mut settings = required_settings_data(json)?;
+        if merge {
+            source.update(&mut settings);
+        } else {
+            source.reset_settings(&mut settings);
+        }
+        Ok(())
+    }
+
+    fn set_playlist(&mut self, source: &str, urls: &[String]) -> Result<(), Status> {
+        let mut source = find_source(source)?;
+        let playlist: Vec<_> = urls.iter().map(|url| PlaylistItem::new(url)).collect();
+        let mut set = || -> anyhow::Result<()> {
+            let mut settings = source.get_settings()?;
+            settings.set_array("playlist", hl::to_array(&playlist)?)?;
+            source.update(&mut settings);
+            Ok(())
+        };
+        set().map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn set_volume(&mut self, source: &str, volume: f32) -> Result<(), Status> {
+        find_source(source)?.set_volume(volume);
+        Ok(())
+    }
+
+    fn set_muted(&mut self, source: &str, muted: bool) -> Result<(), Status> {
+        find_source(source)?.set_muted(muted);
+        Ok(())
+    }
+
+    fn set_balance(&mut self, source: &str, balance: f32) -> Result<(), Status> {
+        find_source(source)?.set_balance(balance);
+        Ok(())
+    }
+
+    fn set_sync_offset(&mut self, source: &str, offset_ns: i64) -> Result<(), Status> {
+        find_source(source)?.set_sync_offset(offset_ns);
+        Ok(())
+    }
+
+    fn set_mixers(&mut self, source: &str, mixers: u32) -> Result<(), Status> {
+        find_source(source)?.set_audio_mixers(mixers);
+        Ok(())
+    }
+
+    fn set_monitoring_type(
+        &mut self,
+        source: &str,
+        monitoring_type: MonitoringType,
+    ) -> Result<(), Status> {
+        find_source(source)?.set_monitoring_type(monitoring_type);
+        Ok(())
+    }
+
+    fn watch_levels(&mut self, sources: &[String]) -> Result<Vec<Box<dyn LevelMeter>>, Status> {
+        let mut watches: Vec<Box<dyn LevelMeter>> = Vec::new();
+        if sources.is_empty() {
+            let mut err = None;
+            Source::for_each(|source: &Source| {
+                if err.is_none() && source.has_audio() {
+                    match LevelWatch::new(source) {
+                        Ok(watch) => watches.push(Box::new(watch)),
+                        Err(e) => err = Some(e),
+                    }
+                }
+            });
+            if let Some(e) = err {
+                return Err(Status::internal(e.to_string()));
+            }
+        } else {
+            for name in sources {
+                let source = find_source(name)?;
+                if !source.has_audio() {
+                    return Err(Status::invalid_argument(format!(
+                        "source {:?} has no audio",
+                        name
+                    )));
+                }
+                let watch =
+                    LevelWatch::new(&source).map_err(|e| Status::internal(e.to_string()))?;
+                watches.push(Box::new(watch));
+            }
+        }
+        Ok(watches)
+    }
+
+    fn scene_items(&self, scene: &str) -> Result<Vec<SceneItemInfo>, Status> {
+        Ok(find_scene(scene)?
+            .items()
+            .iter()
+            .map(scene_item_info)
+            .collect())
+    }
+
+    fn set_item_transform(
+        &mut self,
+        scene: &str,
+        item: &str,
+        transform: &Transform,
+    ) -> Result<(), Status> {
+        let mut item = find_scene_item(scene, item)?;
+        if let Some(position) = transform.position {
+            item.set_pos(position);
+        }
+        if let Some(scale) = transform.scale {
+            item.set_scale(scale);
+        }
+        if let Some(rotation) = transform.rotation {
+            item.set_rot(rotation);
+        }
+        if let Some(bounds_type) = transform.bounds_type {
+            item.set_bounds_type(bounds_type);
+            if let Some(size) = transform.bounds_size {
+                item.set_bounds(size);
+            }
+            if let Some(alignment) = transform.bounds_alignment {
+                item.set_bounds_alignment(alignment);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_item_crop(&mut self, scene: &str, item: &str, crop: Crop) -> Result<(), Status> {
+        find_scene_item(scene, item)?.set_crop(crop);
+        Ok(())
+    }
+
+    fn set_item_visible(&mut self, scene: &str, item: &str, visible: bool) -> Result<(), Status> {
+        find_scene_item(scene, item)?.set_visible(visible);
+        Ok(())
+    }
+
+    fn set_item_order(&mut self, scene: &str, item: &str, order: ItemOrder) -> Result<(), Status> {
+        let mut item = find_scene_item(scene, item)?;
+        match order {
+            ItemOrder::Move(movement) => item.set_order(movement),
+            ItemOrder::Position(position) => item.set_order_position(position),
+        }
+        Ok(())
+    }
+
+    fn filters(&self, source: &str) -> Result<Vec<FilterInfo>, Status> {
+        find_source(source)?
+            .filters()
+            .iter()
+            .map(filter_info)
+            .collect()
+    }
+
+    fn add_filter(
+        &mut self,
+        source: &str,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+    ) -> Result<(), Status> {
+        let mut target = find_source(source)?;
+        if target.get_filter(name).is_ok() {
+            return Err(Status::already_exists(format!(
+                "source {:?} already has a filter {:?}",
+                source, name
+            )));
+        }
+        let mut settings = match settings {
+            Some(json) => settings_data(json)?,
+            None => None,
+        };
+        // Filter::create checks that `kind` is a filter type.
+        target
+            .add_filter(kind, name, settings.as_mut())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(())
+    }
+
+    fn remove_filter(&mut self, source: &str, filter: &str) -> Result<(), Status> {
+        find_source(source)?
+            .remove_filter(filter)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn set_filter_enabled(
+        &mut self,
+        source: &str,
+        filter: &str,
+        enabled: bool,
+    ) -> Result<(), Status> {
+        find_source(source)?
+            .set_filter_enabled(filter, enabled)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn move_filter(
+        &mut self,
+        source: &str,
+        filter: &str,
+        movement: OrderMovement,
+    ) -> Result<(), Status> {
+        find_source(source)?
+            .move_filter(filter, movement)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn set_filter_settings(
+        &mut self,
+        source: &str,
+        filter: &str,
+        json: &str,
+        merge: bool,
+    ) -> Result<(), Status> {
+        let mut filter = find_filter(source, filter)?;
+        let mut settings = required_settings_data(json)?;
+        if merge {
+            filter.as_source_mut().update(&mut settings);
+        } else {
+            filter.as_source_mut().reset_settings(&mut settings);
+        }
+        Ok(())
+    }
+
+    fn set_current_scene(
+        &mut self,
+        scene: &str,
+        transition: Option<&str>,
+        duration_ms: Option<u32>,
+    ) -> Result<(), Status> {
+        let scene = find_scene(scene)?;
+        let transitions = self
+            .session
+            .transitions_mut()
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if let Some(transition) = transition {
+            if !transitions.contains(transition) {
+                return Err(Status::not_found(format!(
+                    "transition {:?} does not exist",
+                    transition
+                )));
+            }
+        }
+        transitions
+            .switch_scene(scene.as_source(), transition, duration_ms)
+            .map_err(|e| Status::failed_precondition(e.to_string()))
+    }
+
+    fn current_scene(&self) -> Result<CurrentScene, Status> {
+        let current = || -> anyhow::Result<CurrentScene> {
+            let transitions = self.session.transitions()?;
+            Ok(CurrentScene {
+                scene: transitions.current_scene()?.get_name()?,
+                transition: transitions.current().get_name()?,
+                duration_ms: transitions.duration_ms(),
+                transitions: transitions.names()?,
+            })
+        };
+        current().map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn modules(&self) -> Vec<ModuleStatus> {
+        self.session.modules().to_vec()
+    }
+
+    fn encoders(&self) -> Result<Vec<EncoderType>, Status> {
+        hl::encoder_types().map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn start_recording(&mut self, name: &str) -> Result<PathBuf, Status> {
+        self.session
+            .recording_mut()
+            .start(name)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Status> {
+        self.session
+            .recording_mut()
+            .stop()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn recording(&self) -> RecordingInfo {
+        let recording = self.session.recording();
+        RecordingInfo {
+            status: recording.status(),
+            path: recording.path().map(|path| path.to_path_buf()),
+        }
+    }
+
+    fn start_replay_buffer(&mut self) -> Result<(), Status> {
+        self.session
+            .replay_buffer_mut()
+            .start()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn stop_replay_buffer(&mut self) -> Result<(), Status> {
+        self.session
+            .replay_buffer_mut()
+            .stop()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn replay_buffer_status(&self) -> OutputStatus {
+        self.session.replay_buffer().status()
+    }
+
+    fn save_replay(&mut self) -> Result<ReplaySaved, Status> {
+        let replay_buffer = self.session.replay_buffer_mut();
+        if replay_buffer.is_saving() {
+            return Err(Status::failed_precondition(
+                "a replay is already being saved",
+            ));
+        }
+        replay_buffer
+            .save()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn outputs(&self) -> Vec<OutputInfo> {
+        self.session.outputs().iter().map(output_info).collect()
+    }
+
+    fn output(&self, name: &str) -> Result<OutputInfo, Status> {
+        self.session
+            .output(name)
+            .map(output_info)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn add_output(&mut self, name: &str, destination: StreamDestination) -> Result<(), Status> {
+        if self.session.has_output(name) {
+            return Err(Status::already_exists(format!(
+                "output {:?} already exists",
+                name
+            )));
+        }
+        let output = self
+            .session
+            .add_output(name, destination)
+            .map_err(|e| Status::internal(e.to_string()))?;
+        reconnect::watch_output(output, &self.output_events)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn remove_output(&mut self, name: &str) -> Result<(), Status> {
+        self.session
+            .remove_output(name)
+            .map_err(|e| Status::not_found(e.to_string()))
+    }
+
+    fn start_output(&mut self, name: &str) -> Result<(), Status> {
+        self.output_mut(name)?
+            .start()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn stop_output(&mut self, name: &str) -> Result<(), Status> {
+        self.output_mut(name)?
+            .stop()
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn set_output_destination(
+        &mut self,
+        name: &str,
+        destination: StreamDestination,
+    ) -> Result<(), Status> {
+        self.output_mut(name)?
+            .set_destination(destination)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
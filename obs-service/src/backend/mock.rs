@@ -0,0 +1,735 @@
+use super::{
+    settings_from_json, Bounds, CurrentScene, FilterInfo, ItemOrder, LevelMeter, ObsBackend,
+    OutputInfo, PlaylistItem, RecordingInfo, SceneItemInfo, SourceInfo, Transform,
+};
+use crate::{
+    events::{Event, EventFeed},
+    types::{
+        AudioLevels, BoundsType, Crop, EncoderType, ModuleStatus, MonitoringType, OrderMovement,
+        OutputEvent, OutputState, OutputStatus, ReplaySaved, SourceEvent, StopCode,
+        StreamDestination, Vec2, MAIN_OUTPUT,
+    },
+};
+use serde_json::{Map, Value};
+use std::{collections::BTreeMap, path::PathBuf, time::Instant};
+use tokio::sync::oneshot;
+use tonic::Status;
+
+// Source type of the sources that act as scenes.
+const SCENE_KIND: &str = "scene";
+const TRANSITIONS: &[&str] = &["Cut", "Fade"];
+const TRANSITION_DURATION_MS: u32 = 300;
+// Channels reported by the level meters.
+const CHANNELS: usize = 2;
+
+struct MockSource {
+    kind: String,
+    settings: Map<String, Value>,
+    volume: f32,
+    muted: bool,
+    balance: f32,
+    sync_offset_ns: i64,
+    mixers: u32,
+    monitoring_type: MonitoringType,
+    filters: Vec<MockFilter>,
+    // Bottom first, only used by scenes.
+    items: Vec<SceneItemInfo>,
+}
+
+struct MockFilter {
+    name: String,
+    kind: String,
+    enabled: bool,
+    settings: Map<String, Value>,
+}
+
+struct MockOutput {
+    name: String,
+    destination: StreamDestination,
+    started: Option<Instant>,
+}
+
+// Levels derived from the source's volume when the meter was created.
+struct MockMeter {
+    name: String,
+    level: f32,
+}
+
+impl LevelMeter for MockMeter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn take(&self) -> Option<AudioLevels> {
+        Some(AudioLevels {
+            magnitude: vec![self.level; CHANNELS],
+            peak: vec![self.level; CHANNELS],
+            input_peak: vec![self.level; CHANNELS],
+        })
+    }
+}
+
+/// An in-memory stand-in for obs, to run the server without libobs, a
+/// display or a network.  Sources and outputs only keep their settings and
+/// state, nothing is rendered or streamed.  Changes are published to the
+/// event feed like libobs' signals would.
+pub struct MockBackend {
+    sources: BTreeMap<String, MockSource>,
+    outputs: Vec<MockOutput>,
+    next_item_id: i64,
+    current_scene: Option<String>,
+    transition: String,
+    recording: Option<Instant>,
+    recording_path: Option<PathBuf>,
+    replay_buffer: Option<Instant>,
+    replays: Vec<PathBuf>,
+    feed: EventFeed,
+}
+
+impl MockBackend {
+    /// Starts out with just the main output and no sources.
+    pub fn new(feed: EventFeed) -> MockBackend {
+        MockBackend {
+            sources: BTreeMap::new(),
+            outputs: vec![MockOutput {
+                name: MAIN_OUTPUT.into(),
+                destination: StreamDestination {
+                    service: None,
+                    server: "rtmp://localhost/live".into(),
+                    key: "".into(),
+                },
+                started: None,
+            }],
+            next_item_id: 1,
+            current_scene: None,
+            transition: TRANSITIONS[1].into(),
+            recording: None,
+            recording_path: None,
+            replay_buffer: None,
+            replays: Vec::new(),
+            feed,
+        }
+    }
+
+    fn publish(&self, event: Event) {
+        // Fails if no client is watching.
+        let _ = self.feed.send(event);
+    }
+
+    fn source(&self, name: &str) -> Result<&MockSource, Status> {
+        self.sources
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("source {:?} does not exist", name)))
+    }
+
+    fn source_mut(&mut self, name: &str) -> Result<&mut MockSource, Status> {
+        self.sources
+            .get_mut(name)
+            .ok_or_else(|| Status::not_found(format!("source {:?} does not exist", name)))
+    }
+
+    fn scene(&self, name: &str) -> Result<&MockSource, Status> {
+        match self.sources.get(name) {
+            Some(source) if source.kind == SCENE_KIND => Ok(source),
+            _ => Err(Status::not_found(format!(
+                "scene {:?} does not exist",
+                name
+            ))),
+        }
+    }
+
+    fn scene_items_mut(&mut self, name: &str) -> Result<&mut Vec<SceneItemInfo>, Status> {
+        self.scene(name)?;
+        Ok(&mut self.source_mut(name)?.items)
+    }
+
+    fn item_mut(&mut self, scene: &str, item: &str) -> Result<&mut SceneItemInfo, Status> {
+        self.scene_items_mut(scene)?
+            .iter_mut()
+            .find(|i| i.source == item)
+            .ok_or_else(|| Status::not_found(format!("scene {:?} has no item {:?}", scene, item)))
+    }
+
+    fn filter_mut(&mut self, source: &str, filter: &str) -> Result<&mut MockFilter, Status> {
+        self.source_mut(source)?
+            .filters
+            .iter_mut()
+            .find(|f| f.name == filter)
+            .ok_or_else(|| {
+                Status::not_found(format!("source {:?} has no filter {:?}", source, filter))
+            })
+    }
+
+    fn output_mut(&mut self, name: &str) -> Result<&mut MockOutput, Status> {
+        self.outputs
+            .iter_mut()
+            .find(|output| output.name == name)
+            .ok_or_else(|| Status::not_found(format!("output {:?} does not exist", name)))
+    }
+}
+
+fn required_settings(json: &str) -> Result<Map<String, Value>, Status> {
+    settings_from_json(json)?.ok_or_else(|| Status::invalid_argument("settings json is required"))
+}
+
+fn update_settings(settings: &mut Map<String, Value>, new: Map<String, Value>, merge: bool) {
+    if merge {
+        settings.extend(new);
+    } else {
+        *settings = new;
+    }
+}
+
+fn status(started: Option<Instant>) -> OutputStatus {
+    let state = match started {
+        Some(_) => OutputState::Active,
+        None => OutputState::Stopped,
+    };
+    OutputStatus {
+        state,
+        uptime: started.map(|started| started.elapsed()).unwrap_or_default(),
+        total_bytes: 0,
+        total_frames: 0,
+        dropped_frames: 0,
+        congestion: 0.0,
+        last_error: None,
+    }
+}
+
+fn output_info(output: &MockOutput) -> OutputInfo {
+    OutputInfo {
+        name: output.name.clone(),
+        destination: output.destination.clone(),
+        status: status(output.started),
+    }
+}
+
+// Moves the element at `from` to `to`, both clamped to the vector.
+fn reorder<T>(items: &mut Vec<T>, from: usize, to: i64) {
+    let item = items.remove(from);
+    let to = to.max(0).min(items.len() as i64) as usize;
+    items.insert(to, item);
+}
+
+// Index `movement` moves an element at `index` to in a vector of `len`
+// elements, the last one being the top.
+fn moved_index(index: usize, len: usize, movement: OrderMovement) -> i64 {
+    match movement {
+        OrderMovement::Up => index as i64 + 1,
+        OrderMovement::Down => index as i64 - 1,
+        OrderMovement::Top => len as i64,
+        OrderMovement::Bottom => 0,
+    }
+}
+
+impl ObsBackend for MockBackend {
+    fn sources(&self) -> Vec<SourceInfo> {
+        self.sources
+            .iter()
+            .map(|(name, source)| {
+                let is_scene = source.kind == SCENE_KIND;
+                SourceInfo {
+                    name: name.clone(),
+                    has_video: true,
+                    has_audio: !is_scene,
+                    is_composite: is_scene,
+                    volume: source.volume,
+                    muted: source.muted,
+                    balance: source.balance,
+                    sync_offset_ns: source.sync_offset_ns,
+                    mixers: source.mixers,
+                    monitoring_type: source.monitoring_type,
+                }
+            })
+            .collect()
+    }
+
+    fn create_source(
+        &mut self,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+        scene: &str,
+    ) -> Result<i64, Status> {
+        if self.sources.contains_key(name) {
+            return Err(Status::already_exists(format!(
+                "source {:?} already exists",
+                name
+            )));
+        }
+        if kind.is_empty() {
+            return Err(Status::invalid_argument("unknown source type \"\""));
+        }
+        let settings = match settings {
+            Some(json) => settings_from_json(json)?,
+            None => None,
+        };
+        let scene_item_id = if scene.is_empty() {
+            0
+        } else {
+            self.scene(scene)?;
+            let id = self.next_item_id;
+            self.next_item_id += 1;
+            self.source_mut(scene)?.items.push(SceneItemInfo {
+                id,
+                source: name.into(),
+                visible: true,
+                position: Vec2::default(),
+                scale: Vec2 { x: 1.0, y: 1.0 },
+                rotation: 0.0,
+                bounds: Bounds {
+                    bounds_type: BoundsType::None,
+                    size: Vec2::default(),
+                    alignment: 0,
+                },
+                crop: Crop::default(),
+            });
+            id
+        };
+
+        self.sources.insert(
+            name.into(),
+            MockSource {
+                kind: kind.into(),
+                settings: settings.unwrap_or_default(),
+                volume: 1.0,
+                muted: false,
+                balance: 0.5,
+                sync_offset_ns: 0,
+                mixers: 0x3f,
+                monitoring_type: MonitoringType::None,
+                filters: Vec::new(),
+                items: Vec::new(),
+            },
+        );
+        if kind == SCENE_KIND && self.current_scene.is_none() {
+            self.current_scene = Some(name.into());
+        }
+        self.publish(Event::Source(SourceEvent::Created {
+            source: name.into(),
+            kind: kind.into(),
+        }));
+        Ok(scene_item_id)
+    }
+
+    fn remove_source(&mut self, name: &str) -> Result<(), Status> {
+        self.sources
+            .remove(name)
+            .ok_or_else(|| Status::not_found(format!("source {:?} does not exist", name)))?;
+        // Like libobs, removing a source removes it from every scene.
+        for source in self.sources.values_mut() {
+            source.items.retain(|item| item.source != name);
+        }
+        if self.current_scene.as_deref() == Some(name) {
+            self.current_scene = None;
+        }
+        self.publish(Event::Source(SourceEvent::Removed {
+            source: name.into(),
+        }));
+        Ok(())
+    }
+
+    fn source_settings(&self, name: &str, pretty: bool) -> Result<String, Status> {
+        let source = self.source(name)?;
+        if pretty {
+            serde_json::to_string_pretty(&source.settings)
+        } else {
+            serde_json::to_string(&source.settings)
+        }
+        .map_err(|e| Status::internal(e.to_string()))
+    }
+
+    fn set_source_settings(&mut self, name: &str, json: &str, merge: bool) -> Result<(), Status> {
+        let settings = required_settings(json)?;
+        update_settings(&mut self.source_mut(name)?.settings, settings, merge);
+        Ok(())
+    }
+
+    fn set_playlist(&mut self, source: &str, urls: &[String]) -> Result<(), Status> {
+        let playlist: Vec<_> = urls.iter().map(|url| PlaylistItem::new(url)).collect();
+        let playlist =
+            serde_json::to_value(&playlist).map_err(|e| Status::internal(e.to_string()))?;
+        self.source_mut(source)?
+            .settings
+            .insert("playlist".into(), playlist);
+        Ok(())
+    }
+
+    fn set_volume(&mut self, source: &str, volume: f32) -> Result<(), Status> {
+        self.source_mut(source)?.volume = volume;
+        self.publish(Event::Source(SourceEvent::VolumeChanged {
+            source: source.into(),
+            volume,
+        }));
+        Ok(())
+    }
+
+    fn set_muted(&mut self, source: &str, muted: bool) -> Result<(), Status> {
+        self.source_mut(source)?.muted = muted;
+        self.publish(Event::Source(SourceEvent::MuteChanged {
+            source: source.into(),
+            muted,
+        }));
+        Ok(())
+    }
+
+    fn set_balance(&mut self, source: &str, balance: f32) -> Result<(), Status> {
+        self.source_mut(source)?.balance = balance;
+        Ok(())
+    }
+
+    fn set_sync_offset(&mut self, source: &str, offset_ns: i64) -> Result<(), Status> {
+        self.source_mut(source)?.sync_offset_ns = offset_ns;
+        Ok(())
+    }
+
+    fn set_mixers(&mut self, source: &str, mixers: u32) -> Result<(), Status> {
+        self.source_mut(source)?.mixers = mixers;
+        Ok(())
+    }
+
+    fn set_monitoring_type(
+        &mut self,
+        source: &str,
+        monitoring_type: MonitoringType,
+    ) -> Result<(), Status> {
+        self.source_mut(source)?.monitoring_type = monitoring_type;
+        Ok(())
+    }
+
+    fn watch_levels(&mut self, sources: &[String]) -> Result<Vec<Box<dyn LevelMeter>>, Status> {
+        let names: Vec<String> = if sources.is_empty() {
+            self.sources
+                .iter()
+                .filter(|(_, source)| source.kind != SCENE_KIND)
+                .map(|(name, _)| name.clone())
+                .collect()
+        } else {
+            for name in sources {
+                if self.source(name)?.kind == SCENE_KIND {
+                    return Err(Status::invalid_argument(format!(
+                        "source {:?} has no audio",
+                        name
+                    )));
+                }
+            }
+            sources.to_vec()
+        };
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let source = &self.sources[&name];
+                let level = if source.muted {
+                    f32::NEG_INFINITY
+                } else {
+                    20.0 * source.volume.log10()
+                };
+                Box::new(MockMeter { name, level }) as Box<dyn LevelMeter>
+            })
+            .collect())
+    }
+
+    fn scene_items(&self, scene: &str) -> Result<Vec<SceneItemInfo>, Status> {
+        Ok(self.scene(scene)?.items.clone())
+    }
+
+    fn set_item_transform(
+        &mut self,
+        scene: &str,
+        item: &str,
+        transform: &Transform,
+    ) -> Result<(), Status> {
+        let item = self.item_mut(scene, item)?;
+        if let Some(position) = transform.position {
+            item.position = position;
+        }
+        if let Some(scale) = transform.scale {
+            item.scale = scale;
+        }
+        if let Some(rotation) = transform.rotation {
+            item.rotation = rotation;
+        }
+        if let Some(bounds_type) = transform.bounds_type {
+            item.bounds.bounds_type = bounds_type;
+            if let Some(size) = transform.bounds_size {
+                item.bounds.size = size;
+            }
+            if let Some(alignment) = transform.bounds_alignment {
+                item.bounds.alignment = alignment;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_item_crop(&mut self, scene: &str, item: &str, crop: Crop) -> Result<(), Status> {
+        self.item_mut(scene, item)?.crop = crop;
+        Ok(())
+    }
+
+    fn set_item_visible(&mut self, scene: &str, item: &str, visible: bool) -> Result<(), Status> {
+        self.item_mut(scene, item)?.visible = visible;
+        Ok(())
+    }
+
+    fn set_item_order(&mut self, scene: &str, item: &str, order: ItemOrder) -> Result<(), Status> {
+        self.item_mut(scene, item)?;
+        let items = self.scene_items_mut(scene)?;
+        let index = items.iter().position(|i| i.source == item).unwrap();
+        let to = match order {
+            ItemOrder::Move(movement) => moved_index(index, items.len(), movement),
+            ItemOrder::Position(position) => position.into(),
+        };
+        reorder(items, index, to);
+        Ok(())
+    }
+
+    fn filters(&self, source: &str) -> Result<Vec<FilterInfo>, Status> {
+        self.source(source)?
+            .filters
+            .iter()
+            .map(|filter| {
+                Ok(FilterInfo {
+                    name: filter.name.clone(),
+                    kind: filter.kind.clone(),
+                    enabled: filter.enabled,
+                    settings_json: serde_json::to_string(&filter.settings)
+                        .map_err(|e| Status::internal(e.to_string()))?,
+                })
+            })
+            .collect()
+    }
+
+    fn add_filter(
+        &mut self,
+        source: &str,
+        kind: &str,
+        name: &str,
+        settings: Option<&str>,
+    ) -> Result<(), Status> {
+        if self.source(source)?.filters.iter().any(|f| f.name == name) {
+            return Err(Status::already_exists(format!(
+                "source {:?} already has a filter {:?}",
+                source, name
+            )));
+        }
+        if kind.is_empty() {
+            return Err(Status::invalid_argument("unknown filter type \"\""));
+        }
+        let settings = match settings {
+            Some(json) => settings_from_json(json)?,
+            None => None,
+        };
+        self.source_mut(source)?.filters.push(MockFilter {
+            name: name.into(),
+            kind: kind.into(),
+            enabled: true,
+            settings: settings.unwrap_or_default(),
+        });
+        Ok(())
+    }
+
+    fn remove_filter(&mut self, source: &str, filter: &str) -> Result<(), Status> {
+        self.filter_mut(source, filter)?;
+        self.source_mut(source)?
+            .filters
+            .retain(|f| f.name != filter);
+        Ok(())
+    }
+
+    fn set_filter_enabled(
+        &mut self,
+        source: &str,
+        filter: &str,
+        enabled: bool,
+    ) -> Result<(), Status> {
+        self.filter_mut(source, filter)?.enabled = enabled;
+        Ok(())
+    }
+
+    fn move_filter(
+        &mut self,
+        source: &str,
+        filter: &str,
+        movement: OrderMovement,
+    ) -> Result<(), Status> {
+        self.filter_mut(source, filter)?;
+        let filters = &mut self.source_mut(source)?.filters;
+        let index = filters.iter().position(|f| f.name == filter).unwrap();
+        // Filters are listed in the order they are applied, "up" is towards
+        // the first one.
+        let to = match movement {
+            OrderMovement::Up => index as i64 - 1,
+            OrderMovement::Down => index as i64 + 1,
+            OrderMovement::Top => 0,
+            OrderMovement::Bottom => filters.len() as i64,
+        };
+        reorder(filters, index, to);
+        Ok(())
+    }
+
+    fn set_filter_settings(
+        &mut self,
+        source: &str,
+        filter: &str,
+        json: &str,
+        merge: bool,
+    ) -> Result<(), Status> {
+        let settings = required_settings(json)?;
+        update_settings(
+            &mut self.filter_mut(source, filter)?.settings,
+            settings,
+            merge,
+        );
+        Ok(())
+    }
+
+    fn set_current_scene(
+        &mut self,
+        scene: &str,
+        transition: Option<&str>,
+        _duration_ms: Option<u32>,
+    ) -> Result<(), Status> {
+        self.scene(scene)?;
+        if let Some(transition) = transition {
+            if !TRANSITIONS.contains(&transition) {
+                return Err(Status::not_found(format!(
+                    "transition {:?} does not exist",
+                    transition
+                )));
+            }
+            self.transition = transition.into();
+        }
+        self.current_scene = Some(scene.into());
+        self.publish(Event::Source(SourceEvent::SceneSwitched {
+            scene: scene.into(),
+        }));
+        Ok(())
+    }
+
+    fn current_scene(&self) -> Result<CurrentScene, Status> {
+        let scene = self
+            .current_scene
+            .clone()
+            .ok_or_else(|| Status::internal("there is no current scene"))?;
+        Ok(CurrentScene {
+            scene,
+            transition: self.transition.clone(),
+            duration_ms: TRANSITION_DURATION_MS,
+            transitions: TRANSITIONS.iter().map(|name| name.to_string()).collect(),
+        })
+    }
+
+    fn modules(&self) -> Vec<ModuleStatus> {
+        Vec::new()
+    }
+
+    fn encoders(&self) -> Result<Vec<EncoderType>, Status> {
+        Ok(Vec::new())
+    }
+
+    fn start_recording(&mut self, name: &str) -> Result<PathBuf, Status> {
+        let path = PathBuf::from(format!("{}.mkv", name));
+        self.recording = Some(Instant::now());
+        self.recording_path = Some(path.clone());
+        Ok(path)
+    }
+
+    fn stop_recording(&mut self) -> Result<(), Status> {
+        self.recording = None;
+        Ok(())
+    }
+
+    fn recording(&self) -> RecordingInfo {
+        RecordingInfo {
+            status: status(self.recording),
+            path: self.recording_path.clone(),
+        }
+    }
+
+    fn start_replay_buffer(&mut self) -> Result<(), Status> {
+        self.replay_buffer = Some(Instant::now());
+        Ok(())
+    }
+
+    fn stop_replay_buffer(&mut self) -> Result<(), Status> {
+        self.replay_buffer = None;
+        Ok(())
+    }
+
+    fn replay_buffer_status(&self) -> OutputStatus {
+        status(self.replay_buffer)
+    }
+
+    // Saved right away, so there is never a save in progress.
+    fn save_replay(&mut self) -> Result<ReplaySaved, Status> {
+        let path = PathBuf::from(format!("Replay {}.mkv", self.replays.len() + 1));
+        self.replays.push(path.clone());
+        let (sender, receiver) = oneshot::channel();
+        let _ = sender.send(Ok(path));
+        Ok(receiver)
+    }
+
+    fn outputs(&self) -> Vec<OutputInfo> {
+        self.outputs.iter().map(output_info).collect()
+    }
+
+    fn output(&self, name: &str) -> Result<OutputInfo, Status> {
+        self.outputs
+            .iter()
+            .find(|output| output.name == name)
+            .map(output_info)
+            .ok_or_else(|| Status::not_found(format!("output {:?} does not exist", name)))
+    }
+
+    fn add_output(&mut self, name: &str, destination: StreamDestination) -> Result<(), Status> {
+        if self.outputs.iter().any(|output| output.name == name) {
+            return Err(Status::already_exists(format!(
+                "output {:?} already exists",
+                name
+            )));
+        }
+        self.outputs.push(MockOutput {
+            name: name.into(),
+            destination,
+            started: None,
+        });
+        Ok(())
+    }
+
+    fn remove_output(&mut self, name: &str) -> Result<(), Status> {
+        let index = self
+            .outputs
+            .iter()
+            .position(|output| output.name == name)
+            .ok_or_else(|| Status::not_found(format!("output {:?} does not exist", name)))?;
+        self.outputs.remove(index);
+        Ok(())
+    }
+
+    fn start_output(&mut self, name: &str) -> Result<(), Status> {
+        self.output_mut(name)?.started = Some(Instant::now());
+        self.publish(Event::Output {
+            name: name.into(),
+            event: OutputEvent::Started,
+        });
+        Ok(())
+    }
+
+    fn stop_output(&mut self, name: &str) -> Result<(), Status> {
+        self.output_mut(name)?.started = None;
+        self.publish(Event::Output {
+            name: name.into(),
+            event: OutputEvent::Stopped(StopCode::Success),
+        });
+        Ok(())
+    }
+
+    fn set_output_destination(
+        &mut self,
+        name: &str,
+        destination: StreamDestination,
+    ) -> Result<(), Status> {
+        self.output_mut(name)?.destination = destination;
+        Ok(())
+    }
+}
@@ -0,0 +1,1185 @@
+use crate::types::{
+    AudioEncoderSettings, DisplaySettings, ModulePath, ModuleSettings, Platform, ReconnectPolicy,
+    RecordingFormat, RecordingSettings, ReplayBufferSettings, SessionSettings, StreamDestination,
+    VideoEncoderSettings, MAIN_OUTPUT,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap, fmt, fs, net::SocketAddr, path::PathBuf, str::FromStr, time::Duration,
+};
+use structopt::StructOpt;
+
+const RATE_CONTROLS: &[&str] = &["CBR", "ABR", "VBR", "CRF", "CQP", "VBR_LAT", "LOSSLESS"];
+// Presets, profiles and tunes are only checked for obs_x264.  Other encoders
+// get them passed through as is.
+const X264_ID: &str = "obs_x264";
+const X264_PRESETS: &[&str] = &[
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+// The modules providing the encoders that are checked for.  An encoder from
+// a module that isn't required would only fail once the session starts.
+const ENCODER_MODULES: &[(&str, &str)] = &[
+    (X264_ID, "obs-x264"),
+    ("ffmpeg_aac", "obs-ffmpeg"),
+    ("ffmpeg_nvenc", "obs-ffmpeg"),
+    ("ffmpeg_vaapi", "obs-ffmpeg"),
+    ("libfdk_aac", "obs-libfdk"),
+];
+const X264_PROFILES: &[&str] = &["", "baseline", "main", "high"];
+const X264_TUNES: &[&str] = &[
+    "",
+    "film",
+    "animation",
+    "grain",
+    "stillimage",
+    "psnr",
+    "ssim",
+    "fastdecode",
+    "zerolatency",
+];
+const SAMPLE_RATES: &[u32] = &[44100, 48000];
+
+/// Command line options.  Every option can also be set through the listed
+/// environment variable and overrides the value from the config file.
+// Not Debug, so the stream key can't end up in logs.
+#[derive(StructOpt)]
+#[structopt(name = "obs-service")]
+pub struct Opt {
+    /// TOML config file.
+    #[structopt(short, long, env = "OBS_SERVICE_CONFIG", parse(from_os_str))]
+    config: Option<PathBuf>,
+
+    /// Address the gRPC server listens on.
+    #[structopt(long, env = "OBS_SERVICE_LISTEN_ADDR")]
+    listen_addr: Option<SocketAddr>,
+
+    /// Scene collection exported by obs.
+    #[structopt(long, env = "OBS_SERVICE_SCENE_COLLECTION", parse(from_os_str))]
+    scene_collection: Option<PathBuf>,
+
+    /// Path or name of the libobs graphics module.
+    #[structopt(long, env = "OBS_SERVICE_GRAPHICS_MODULE")]
+    graphics_module: Option<String>,
+
+    /// Module search path as BIN_DIR:DATA_DIR.  DATA_DIR may contain
+    /// `%module%`.  Replaces the paths from the config file.
+    #[structopt(long, env = "OBS_SERVICE_MODULE_PATHS", use_delimiter = true)]
+    module_path: Vec<ModulePathConfig>,
+
+    /// How libobs renders: glx or egl.
+    #[structopt(long, env = "OBS_SERVICE_PLATFORM")]
+    platform: Option<String>,
+
+    /// X display to render on, instead of $DISPLAY.
+    #[structopt(long, env = "OBS_SERVICE_X_DISPLAY")]
+    x_display: Option<String>,
+
+    /// Start a private Xvfb when there is no X display, true or false.
+    #[structopt(long, env = "OBS_SERVICE_XVFB")]
+    xvfb: Option<bool>,
+
+    #[structopt(long, env = "OBS_SERVICE_BASE_WIDTH")]
+    base_width: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_BASE_HEIGHT")]
+    base_height: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_OUTPUT_WIDTH")]
+    output_width: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_OUTPUT_HEIGHT")]
+    output_height: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_FPS")]
+    fps: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_AUDIO_SAMPLES_PER_SEC")]
+    audio_samples_per_sec: Option<u32>,
+
+    /// rtmp_common service name, e.g. Twitch.  Empty for a custom server.
+    #[structopt(long, env = "OBS_SERVICE_STREAM_SERVICE")]
+    stream_service: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_STREAM_URL")]
+    stream_url: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_STREAM_KEY", hide_env_values = true)]
+    stream_key: Option<String>,
+
+    /// Video encoder id, e.g. obs_x264, ffmpeg_nvenc or ffmpeg_vaapi.
+    #[structopt(long, env = "OBS_SERVICE_VIDEO_ENCODER")]
+    video_encoder: Option<String>,
+
+    /// Video bitrate in kbps.
+    #[structopt(long, env = "OBS_SERVICE_VIDEO_BITRATE")]
+    video_bitrate: Option<u32>,
+
+    /// Keyframe interval in seconds.  0 lets the encoder decide.
+    #[structopt(long, env = "OBS_SERVICE_KEYINT_SEC")]
+    keyint_sec: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_RATE_CONTROL")]
+    rate_control: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_PRESET")]
+    preset: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_PROFILE")]
+    profile: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_TUNE")]
+    tune: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_X264OPTS")]
+    x264opts: Option<String>,
+
+    /// Audio encoder id, e.g. ffmpeg_aac or libfdk_aac.
+    #[structopt(long, env = "OBS_SERVICE_AUDIO_ENCODER")]
+    audio_encoder: Option<String>,
+
+    /// Audio bitrate in kbps.
+    #[structopt(long, env = "OBS_SERVICE_AUDIO_BITRATE")]
+    audio_bitrate: Option<u32>,
+
+    #[structopt(long, env = "OBS_SERVICE_RECORDING_DIRECTORY", parse(from_os_str))]
+    recording_directory: Option<PathBuf>,
+
+    /// Recording file name without extension.  `{name}` is replaced by the
+    /// recording's name and `%CCYY-%MM-%DD %hh-%mm-%ss` by the date and time.
+    #[structopt(long, env = "OBS_SERVICE_RECORDING_FILENAME")]
+    recording_filename: Option<String>,
+
+    /// mkv, mp4 or flv.
+    #[structopt(long, env = "OBS_SERVICE_RECORDING_FORMAT")]
+    recording_format: Option<String>,
+
+    #[structopt(long, env = "OBS_SERVICE_REPLAY_DIRECTORY", parse(from_os_str))]
+    replay_directory: Option<PathBuf>,
+
+    /// Replay file name without extension, e.g.
+    /// `Replay %CCYY-%MM-%DD %hh-%mm-%ss`.
+    #[structopt(long, env = "OBS_SERVICE_REPLAY_FILENAME")]
+    replay_filename: Option<String>,
+
+    /// mkv, mp4 or flv.
+    #[structopt(long, env = "OBS_SERVICE_REPLAY_FORMAT")]
+    replay_format: Option<String>,
+
+    /// Seconds kept in the replay buffer.
+    #[structopt(long, env = "OBS_SERVICE_REPLAY_MAX_TIME_SEC")]
+    replay_max_time_sec: Option<u32>,
+
+    /// Memory limit of the replay buffer in megabytes.
+    #[structopt(long, env = "OBS_SERVICE_REPLAY_MAX_SIZE_MB")]
+    replay_max_size_mb: Option<u32>,
+
+    /// Seconds before the first reconnect of a failed stream.
+    #[structopt(long, env = "OBS_SERVICE_RECONNECT_DELAY_SEC")]
+    reconnect_delay_sec: Option<f32>,
+
+    /// Longest delay between reconnects in seconds.
+    #[structopt(long, env = "OBS_SERVICE_RECONNECT_MAX_DELAY_SEC")]
+    reconnect_max_delay_sec: Option<f32>,
+
+    /// Factor the reconnect delay grows by after each failed retry.
+    #[structopt(long, env = "OBS_SERVICE_RECONNECT_BACKOFF")]
+    reconnect_backoff: Option<f32>,
+
+    /// Reconnect attempts before giving up, zero to never reconnect.
+    #[structopt(long, env = "OBS_SERVICE_RECONNECT_MAX_RETRIES")]
+    reconnect_max_retries: Option<u32>,
+
+    /// Serve an in-memory mock of obs instead of libobs, e.g. to test
+    /// clients.  Nothing but the listen address is used from the config.
+    #[structopt(long)]
+    mock: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen_addr: SocketAddr,
+    /// Serve the mock backend instead of libobs.
+    pub mock: bool,
+    pub scene_collection: PathBuf,
+    pub modules: ModulesConfig,
+    pub display: DisplayConfig,
+    pub video: VideoConfig,
+    pub audio: AudioConfig,
+    /// The main output.
+    pub stream: StreamConfig,
+    /// More outputs by name, sharing the main output's encoders.
+    pub outputs: BTreeMap<String, StreamConfig>,
+    pub video_encoder: VideoEncoderConfig,
+    pub audio_encoder: AudioEncoderConfig,
+    pub recording: RecordingConfig,
+    pub replay_buffer: ReplayBufferConfig,
+    pub reconnect: ReconnectConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModulesConfig {
+    pub graphics_module: String,
+    pub paths: Vec<ModulePathConfig>,
+    pub required: Vec<String>,
+    pub optional: Vec<String>,
+    pub load_all: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModulePathConfig {
+    pub bin: String,
+    pub data: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// glx or egl.
+    pub platform: String,
+    /// X display name.  Empty for $DISPLAY.
+    pub display: String,
+    /// Start a private Xvfb server when there is no display.
+    pub xvfb: bool,
+    pub xvfb_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoConfig {
+    pub base_width: u32,
+    pub base_height: u32,
+    pub output_width: u32,
+    pub output_height: u32,
+    pub fps: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub samples_per_sec: u32,
+}
+
+// Debug is implemented through StreamDestination to keep the key out of logs.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StreamConfig {
+    /// An rtmp_common service name, e.g. "Twitch".  Empty for a custom
+    /// server.
+    pub service: String,
+    pub url: String,
+    pub key: String,
+    /// Start streaming as soon as the service is up.
+    pub autostart: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoEncoderConfig {
+    pub id: String,
+    pub bitrate: u32,
+    pub keyint_sec: u32,
+    pub rate_control: String,
+    pub preset: String,
+    pub profile: String,
+    pub tune: String,
+    pub x264opts: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioEncoderConfig {
+    pub id: String,
+    pub bitrate: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordingConfig {
+    pub directory: PathBuf,
+    /// File name without extension.  See `RecordingSettings::filename_format`.
+    pub filename: String,
+    /// mkv, mp4 or flv.
+    pub format: String,
+    /// Dedicated recording encoders.  The stream's encoders are shared
+    /// unless at least one of these is set.
+    pub video_encoder: Option<VideoEncoderConfig>,
+    pub audio_encoder: Option<AudioEncoderConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplayBufferConfig {
+    pub directory: PathBuf,
+    /// File name without extension, e.g. `Replay %CCYY-%MM-%DD %hh-%mm-%ss`.
+    pub filename: String,
+    /// mkv, mp4 or flv.
+    pub format: String,
+    pub max_time_sec: u32,
+    pub max_size_mb: u32,
+    /// Start buffering as soon as the service is up.
+    pub autostart: bool,
+}
+
+/// Applies to every stream output.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    pub retry_delay_sec: f32,
+    pub max_retry_delay_sec: f32,
+    /// Zero to never reconnect.
+    pub max_retries: u32,
+    /// Factor the delay grows by after each failed retry.
+    pub backoff: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            listen_addr: "[::1]:50051".parse().unwrap(),
+            mock: false,
+            scene_collection: "obs.json".into(),
+            modules: ModulesConfig::default(),
+            display: DisplayConfig::default(),
+            video: VideoConfig::default(),
+            audio: AudioConfig::default(),
+            stream: StreamConfig::default(),
+            outputs: BTreeMap::new(),
+            video_encoder: VideoEncoderConfig::default(),
+            audio_encoder: AudioEncoderConfig::default(),
+            recording: RecordingConfig::default(),
+            replay_buffer: ReplayBufferConfig::default(),
+            reconnect: ReconnectConfig::default(),
+        }
+    }
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        DisplayConfig {
+            platform: "glx".into(),
+            display: "".into(),
+            xvfb: false,
+            xvfb_path: "Xvfb".into(),
+        }
+    }
+}
+
+impl Default for ModulesConfig {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|&name| name.into()).collect();
+        ModulesConfig {
+            graphics_module: "/usr/lib/libobs-opengl.so.0.0".into(),
+            paths: vec![ModulePathConfig {
+                bin: "/usr/lib/obs-plugins/".into(),
+                data: "/usr/share/obs/obs-plugins/%module%".into(),
+            }],
+            required: names(&[
+                "obs-transitions",
+                "rtmp-services",
+                "obs-x264",
+                "obs-ffmpeg",
+                "obs-outputs",
+            ]),
+            optional: names(&["image-source", "obs-libfdk", "vlc-video", "obs-browser"]),
+            load_all: false,
+        }
+    }
+}
+
+impl FromStr for ModulePathConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some((bin, data)) if !bin.is_empty() && !data.is_empty() => Ok(ModulePathConfig {
+                bin: bin.into(),
+                data: data.into(),
+            }),
+            _ => Err(anyhow!("module path must be BIN_DIR:DATA_DIR, got {:?}", s)),
+        }
+    }
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        VideoConfig {
+            base_width: 1280,
+            base_height: 720,
+            output_width: 1280,
+            output_height: 720,
+            fps: 30,
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            samples_per_sec: 48000,
+        }
+    }
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        StreamConfig {
+            service: "".into(),
+            url: "rtmp://localhost:1935/live/".into(),
+            key: "key".into(),
+            autostart: true,
+        }
+    }
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig {
+            directory: "recordings".into(),
+            filename: "%CCYY-%MM-%DD %hh-%mm-%ss {name}".into(),
+            format: "mkv".into(),
+            video_encoder: None,
+            audio_encoder: None,
+        }
+    }
+}
+
+impl Default for ReplayBufferConfig {
+    fn default() -> Self {
+        ReplayBufferConfig {
+            directory: "replays".into(),
+            filename: "Replay %CCYY-%MM-%DD %hh-%mm-%ss".into(),
+            format: "mkv".into(),
+            max_time_sec: 20,
+            max_size_mb: 512,
+            autostart: false,
+        }
+    }
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        ReconnectConfig {
+            retry_delay_sec: 2.0,
+            max_retry_delay_sec: 60.0,
+            max_retries: 20,
+            backoff: 1.5,
+        }
+    }
+}
+
+impl StreamConfig {
+    pub fn destination(&self) -> StreamDestination {
+        StreamDestination {
+            service: Some(self.service.clone()).filter(|service| !service.is_empty()),
+            server: self.url.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for StreamConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamConfig")
+            .field("destination", &self.destination())
+            .field("autostart", &self.autostart)
+            .finish()
+    }
+}
+
+impl Default for VideoEncoderConfig {
+    fn default() -> Self {
+        VideoEncoderConfig {
+            id: X264_ID.into(),
+            bitrate: 2000,
+            keyint_sec: 2,
+            rate_control: "CBR".into(),
+            preset: "ultrafast".into(),
+            profile: "main".into(),
+            tune: "zerolatency".into(),
+            x264opts: "".into(),
+        }
+    }
+}
+
+impl Default for AudioEncoderConfig {
+    fn default() -> Self {
+        AudioEncoderConfig {
+            id: "ffmpeg_aac".into(),
+            bitrate: 160,
+        }
+    }
+}
+
+impl Config {
+    /// Builds the config from the defaults, the config file named in `opt`
+    /// and finally the command line and environment overrides.
+    pub fn load(opt: Opt) -> Result<Config> {
+        let mut config = match &opt.config {
+            Some(path) => {
+                let toml_str = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read config {:?}", path))?;
+                toml::from_str(&toml_str)
+                    .with_context(|| format!("failed to parse config {:?}", path))?
+            }
+            None => Config::default(),
+        };
+        config.apply(opt);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply(&mut self, opt: Opt) {
+        fn set<T>(value: &mut T, opt: Option<T>) {
+            if let Some(opt) = opt {
+                *value = opt;
+            }
+        }
+
+        set(&mut self.listen_addr, opt.listen_addr);
+        self.mock |= opt.mock;
+        set(&mut self.scene_collection, opt.scene_collection);
+
+        set(&mut self.modules.graphics_module, opt.graphics_module);
+        if !opt.module_path.is_empty() {
+            self.modules.paths = opt.module_path;
+        }
+
+        let display = &mut self.display;
+        set(&mut display.platform, opt.platform);
+        set(&mut display.display, opt.x_display);
+        set(&mut display.xvfb, opt.xvfb);
+
+        let video = &mut self.video;
+        set(&mut video.base_width, opt.base_width);
+        set(&mut video.base_height, opt.base_height);
+        set(&mut video.output_width, opt.output_width);
+        set(&mut video.output_height, opt.output_height);
+        set(&mut video.fps, opt.fps);
+
+        set(&mut self.audio.samples_per_sec, opt.audio_samples_per_sec);
+
+        set(&mut self.stream.service, opt.stream_service);
+        set(&mut self.stream.url, opt.stream_url);
+        set(&mut self.stream.key, opt.stream_key);
+
+        let encoder = &mut self.video_encoder;
+        set(&mut encoder.id, opt.video_encoder);
+        set(&mut encoder.bitrate, opt.video_bitrate);
+        set(&mut encoder.keyint_sec, opt.keyint_sec);
+        set(&mut encoder.rate_control, opt.rate_control);
+        set(&mut encoder.preset, opt.preset);
+        set(&mut encoder.profile, opt.profile);
+        set(&mut encoder.tune, opt.tune);
+        set(&mut encoder.x264opts, opt.x264opts);
+
+        let encoder = &mut self.audio_encoder;
+        set(&mut encoder.id, opt.audio_encoder);
+        set(&mut encoder.bitrate, opt.audio_bitrate);
+
+        let recording = &mut self.recording;
+        set(&mut recording.directory, opt.recording_directory);
+        set(&mut recording.filename, opt.recording_filename);
+        set(&mut recording.format, opt.recording_format);
+
+        let replay_buffer = &mut self.replay_buffer;
+        set(&mut replay_buffer.directory, opt.replay_directory);
+        set(&mut replay_buffer.filename, opt.replay_filename);
+        set(&mut replay_buffer.format, opt.replay_format);
+        set(&mut replay_buffer.max_time_sec, opt.replay_max_time_sec);
+        set(&mut replay_buffer.max_size_mb, opt.replay_max_size_mb);
+
+        let reconnect = &mut self.reconnect;
+        set(&mut reconnect.retry_delay_sec, opt.reconnect_delay_sec);
+        set(
+            &mut reconnect.max_retry_delay_sec,
+            opt.reconnect_max_delay_sec,
+        );
+        set(&mut reconnect.backoff, opt.reconnect_backoff);
+        set(&mut reconnect.max_retries, opt.reconnect_max_retries);
+    }
+
+    fn validate(&self) -> Result<()> {
+        let modules = &self.modules;
+        if modules.graphics_module.is_empty() {
+            return Err(anyhow!("modules.graphics_module must not be empty"));
+        }
+        if modules.paths.is_empty() {
+            return Err(anyhow!("modules.paths must not be empty"));
+        }
+        if let Some(name) = modules
+            .required
+            .iter()
+            .find(|name| modules.optional.contains(name))
+        {
+            return Err(anyhow!(
+                "module {:?} is listed as both required and optional",
+                name
+            ));
+        }
+
+        display_platform("display.platform", &self.display.platform)?;
+        if self.display.xvfb && self.display.xvfb_path.is_empty() {
+            return Err(anyhow!("display.xvfb_path must not be empty"));
+        }
+
+        let video = &self.video;
+        check_dimension("video.base_width", video.base_width)?;
+        check_dimension("video.base_height", video.base_height)?;
+        check_dimension("video.output_width", video.output_width)?;
+        check_dimension("video.output_height", video.output_height)?;
+        if video.fps == 0 || video.fps > 240 {
+            return Err(anyhow!(
+                "video.fps must be between 1 and 240, got {}",
+                video.fps
+            ));
+        }
+
+        if !SAMPLE_RATES.contains(&self.audio.samples_per_sec) {
+            return Err(anyhow!(
+                "audio.samples_per_sec must be one of {:?}, got {}",
+                SAMPLE_RATES,
+                self.audio.samples_per_sec
+            ));
+        }
+
+        self.stream
+            .destination()
+            .validate()
+            .context("invalid stream config")?;
+        for (name, stream) in &self.outputs {
+            if name.is_empty() || name == MAIN_OUTPUT {
+                return Err(anyhow!("invalid output name {:?}", name));
+            }
+            stream
+                .destination()
+                .validate()
+                .with_context(|| format!("invalid config for output {:?}", name))?;
+        }
+
+        self.video_encoder.validate("video_encoder")?;
+        self.audio_encoder.validate("audio_encoder")?;
+        self.check_encoder_module("video_encoder", &self.video_encoder.id)?;
+        self.check_encoder_module("audio_encoder", &self.audio_encoder.id)?;
+
+        let recording = &self.recording;
+        recording_format("recording.format", &recording.format)?;
+        if recording.filename.is_empty() {
+            return Err(anyhow!("recording.filename must not be empty"));
+        }
+        if let Some(encoder) = &recording.video_encoder {
+            encoder.validate("recording.video_encoder")?;
+            self.check_encoder_module("recording.video_encoder", &encoder.id)?;
+        }
+        if let Some(encoder) = &recording.audio_encoder {
+            encoder.validate("recording.audio_encoder")?;
+            self.check_encoder_module("recording.audio_encoder", &encoder.id)?;
+        }
+
+        let replay_buffer = &self.replay_buffer;
+        recording_format("replay_buffer.format", &replay_buffer.format)?;
+        if replay_buffer.filename.is_empty() {
+            return Err(anyhow!("replay_buffer.filename must not be empty"));
+        }
+        if replay_buffer.max_time_sec == 0 || replay_buffer.max_time_sec > 21600 {
+            return Err(anyhow!(
+                "replay_buffer.max_time_sec must be between 1 and 21600, got {}",
+                replay_buffer.max_time_sec
+            ));
+        }
+        if replay_buffer.max_size_mb == 0 {
+            return Err(anyhow!("replay_buffer.max_size_mb must be greater than 0"));
+        }
+
+        let reconnect = &self.reconnect;
+        if !(0.0..=3600.0).contains(&reconnect.retry_delay_sec) {
+            return Err(anyhow!(
+                "reconnect.retry_delay_sec must be between 0 and 3600, got {}",
+                reconnect.retry_delay_sec
+            ));
+        }
+        if !(reconnect.retry_delay_sec..=3600.0).contains(&reconnect.max_retry_delay_sec) {
+            return Err(anyhow!(
+                "reconnect.max_retry_delay_sec must be between retry_delay_sec and 3600, got {}",
+                reconnect.max_retry_delay_sec
+            ));
+        }
+        if !(1.0..=10.0).contains(&reconnect.backoff) {
+            return Err(anyhow!(
+                "reconnect.backoff must be between 1 and 10, got {}",
+                reconnect.backoff
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn check_encoder_module(&self, section: &str, id: &str) -> Result<()> {
+        let module = ENCODER_MODULES
+            .iter()
+            .find(|(encoder, _)| *encoder == id)
+            .map(|(_, module)| *module);
+        match module {
+            Some(module) if !self.modules.required.iter().any(|name| name == module) => {
+                Err(anyhow!(
+                    "{}.id {:?} needs {:?} in modules.required",
+                    section,
+                    id,
+                    module
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns every output's config, starting with the main output.
+    pub fn streams(&self) -> impl Iterator<Item = (&str, &StreamConfig)> {
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|(name, stream)| (name.as_str(), stream));
+        std::iter::once((MAIN_OUTPUT, &self.stream)).chain(outputs)
+    }
+
+    pub fn reconnect_policy(&self) -> ReconnectPolicy {
+        let reconnect = &self.reconnect;
+        ReconnectPolicy {
+            retry_delay: Duration::from_secs_f32(reconnect.retry_delay_sec),
+            max_retry_delay: Duration::from_secs_f32(reconnect.max_retry_delay_sec),
+            max_retries: reconnect.max_retries,
+            backoff: reconnect.backoff,
+        }
+    }
+
+    pub fn session_settings(&self) -> SessionSettings {
+        let modules = &self.modules;
+        let display = &self.display;
+        let recording = &self.recording;
+        let replay_buffer = &self.replay_buffer;
+        SessionSettings {
+            graphics_module: modules.graphics_module.clone(),
+            display: DisplaySettings {
+                // Checked by validate().
+                platform: display_platform("display.platform", &display.platform).unwrap(),
+                display: Some(display.display.clone()).filter(|name| !name.is_empty()),
+                xvfb: display.xvfb,
+                xvfb_path: display.xvfb_path.clone(),
+            },
+            modules: ModuleSettings {
+                paths: modules
+                    .paths
+                    .iter()
+                    .map(|path| ModulePath {
+                        bin: path.bin.clone(),
+                        data: path.data.clone(),
+                    })
+                    .collect(),
+                required: modules.required.clone(),
+                optional: modules.optional.clone(),
+                load_all: modules.load_all,
+            },
+            base_width: self.video.base_width,
+            base_height: self.video.base_height,
+            output_width: self.video.output_width,
+            output_height: self.video.output_height,
+            fps: self.video.fps,
+            audio_samples_per_sec: self.audio.samples_per_sec,
+            stream_destination: self.stream.destination(),
+            video_encoder: self.video_encoder.settings(),
+            audio_encoder: self.audio_encoder.settings(),
+            recording: RecordingSettings {
+                directory: recording.directory.clone(),
+                filename_format: recording.filename.clone(),
+                // Checked by validate().
+                format: recording_format("recording.format", &recording.format).unwrap(),
+                encoders: match (&recording.video_encoder, &recording.audio_encoder) {
+                    (None, None) => None,
+                    (video, audio) => Some((
+                        video.as_ref().unwrap_or(&Default::default()).settings(),
+                        audio.as_ref().unwrap_or(&Default::default()).settings(),
+                    )),
+                },
+            },
+            replay_buffer: ReplayBufferSettings {
+                directory: replay_buffer.directory.clone(),
+                filename_format: replay_buffer.filename.clone(),
+                // Checked by validate().
+                format: recording_format("replay_buffer.format", &replay_buffer.format).unwrap(),
+                max_time_sec: replay_buffer.max_time_sec,
+                max_size_mb: replay_buffer.max_size_mb,
+            },
+        }
+    }
+}
+
+impl VideoEncoderConfig {
+    fn validate(&self, section: &str) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("{}.id must not be empty", section));
+        }
+        if self.bitrate == 0 {
+            return Err(anyhow!("{}.bitrate must be greater than 0", section));
+        }
+        check_choice(
+            &format!("{}.rate_control", section),
+            &self.rate_control,
+            RATE_CONTROLS,
+        )?;
+        if self.id == X264_ID {
+            check_choice(&format!("{}.preset", section), &self.preset, X264_PRESETS)?;
+            check_choice(
+                &format!("{}.profile", section),
+                &self.profile,
+                X264_PROFILES,
+            )?;
+            check_choice(&format!("{}.tune", section), &self.tune, X264_TUNES)?;
+        } else if !self.x264opts.is_empty() {
+            return Err(anyhow!(
+                "{}.x264opts is only supported by {}, not {:?}",
+                section,
+                X264_ID,
+                self.id
+            ));
+        }
+        Ok(())
+    }
+
+    fn settings(&self) -> VideoEncoderSettings {
+        VideoEncoderSettings {
+            id: self.id.clone(),
+            bitrate: self.bitrate,
+            keyint_sec: self.keyint_sec,
+            rate_control: self.rate_control.clone(),
+            preset: self.preset.clone(),
+            profile: self.profile.clone(),
+            tune: self.tune.clone(),
+            x264opts: self.x264opts.clone(),
+        }
+    }
+}
+
+impl AudioEncoderConfig {
+    fn validate(&self, section: &str) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("{}.id must not be empty", section));
+        }
+        if self.bitrate == 0 || self.bitrate > 1024 {
+            return Err(anyhow!(
+                "{}.bitrate must be between 1 and 1024 kbps, got {}",
+                section,
+                self.bitrate
+            ));
+        }
+        Ok(())
+    }
+
+    fn settings(&self) -> AudioEncoderSettings {
+        AudioEncoderSettings {
+            id: self.id.clone(),
+            bitrate: self.bitrate,
+        }
+    }
+}
+
+fn recording_format(name: &str, format: &str) -> Result<RecordingFormat> {
+    match format {
+        "mkv" => Ok(RecordingFormat::Mkv),
+        "mp4" => Ok(RecordingFormat::Mp4),
+        "flv" => Ok(RecordingFormat::Flv),
+        _ => Err(anyhow!(
+            "{} must be one of mkv, mp4 or flv, got {:?}",
+            name,
+            format
+        )),
+    }
+}
+
+fn display_platform(name: &str, platform: &str) -> Result<Platform> {
+    match platform {
+        "glx" => Ok(Platform::Glx),
+        "egl" => Ok(Platform::Egl),
+        _ => Err(anyhow!(
+            "{} must be one of glx or egl, got {:?}",
+            name,
+            platform
+        )),
+    }
+}
+
+// NV12 output needs even dimensions.
+fn check_dimension(name: &str, value: u32) -> Result<()> {
+    if value == 0 || !value.is_multiple_of(2) || value > 16384 {
+        return Err(anyhow!(
+            "{} must be an even number between 2 and 16384, got {}",
+            name,
+            value
+        ));
+    }
+    Ok(())
+}
+
+fn check_choice(name: &str, value: &str, choices: &[&str]) -> Result<()> {
+    if !choices.contains(&value) {
+        return Err(anyhow!(
+            "{} must be one of {:?}, got {:?}",
+            name,
+            choices,
+            value
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, iter, process, sync::Mutex};
+
+    // Every Opt reads all OBS_SERVICE_* variables, so tests that parse one
+    // must not run while another one changes the environment.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn opt(args: &[&str]) -> Opt {
+        Opt::from_iter_safe(iter::once("obs-service").chain(args.iter().copied())).unwrap()
+    }
+
+    // Writes `toml` to a file that is removed again when dropped.
+    struct ConfigFile(PathBuf);
+
+    impl ConfigFile {
+        fn new(name: &str, toml: &str) -> ConfigFile {
+            let path = env::temp_dir().join(format!("obs-service-{}-{}.toml", process::id(), name));
+            fs::write(&path, toml).unwrap();
+            ConfigFile(path)
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for ConfigFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn load(args: &[&str]) -> Result<Config> {
+        let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        Config::load(opt(args))
+    }
+
+    // Applies `args` to the default config and validates it.
+    fn error(args: &[&str]) -> String {
+        format!("{:#}", load(args).unwrap_err())
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        Config::default().validate().unwrap();
+    }
+
+    #[test]
+    fn default_audio_encoder_needs_no_optional_module() {
+        let config = Config::default();
+        assert_eq!(config.audio_encoder.id, "ffmpeg_aac");
+        assert!(config
+            .modules
+            .required
+            .iter()
+            .any(|name| name == "obs-ffmpeg"));
+    }
+
+    #[test]
+    fn encoder_module_must_be_required() {
+        let mut config = Config::default();
+        config.audio_encoder.id = "libfdk_aac".into();
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("obs-libfdk"), "{}", error);
+
+        config.modules.optional.retain(|name| name != "obs-libfdk");
+        config.modules.required.push("obs-libfdk".into());
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn cli_beats_env_beats_file() {
+        let file = ConfigFile::new(
+            "precedence",
+            r#"
+                [video]
+                fps = 25
+                base_width = 1920
+
+                [audio_encoder]
+                bitrate = 128
+            "#,
+        );
+        let config = {
+            let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+            env::set_var("OBS_SERVICE_FPS", "50");
+            env::set_var("OBS_SERVICE_BASE_WIDTH", "1280");
+            let opt = opt(&["--config", file.path(), "--fps", "60"]);
+            env::remove_var("OBS_SERVICE_FPS");
+            env::remove_var("OBS_SERVICE_BASE_WIDTH");
+            Config::load(opt).unwrap()
+        };
+        assert_eq!(config.video.fps, 60);
+        assert_eq!(config.video.base_width, 1280);
+        assert_eq!(config.audio_encoder.bitrate, 128);
+        // Not set anywhere.
+        assert_eq!(config.video.base_height, VideoConfig::default().base_height);
+    }
+
+    #[test]
+    fn overrides() {
+        let config = load(&[
+            "--replay-filename",
+            "clip %hh-%mm",
+            "--replay-format",
+            "mp4",
+            "--reconnect-delay-sec",
+            "1",
+            "--reconnect-max-delay-sec",
+            "30",
+            "--reconnect-backoff",
+            "2",
+            "--reconnect-max-retries",
+            "5",
+            "--xvfb",
+            "true",
+            "--module-path",
+            "/bin:/data/%module%",
+        ])
+        .unwrap();
+        assert_eq!(config.replay_buffer.filename, "clip %hh-%mm");
+        assert_eq!(config.replay_buffer.format, "mp4");
+        assert!(config.display.xvfb);
+        assert_eq!(config.modules.paths.len(), 1);
+        assert_eq!(config.modules.paths[0].bin, "/bin");
+
+        let policy = config.reconnect_policy();
+        assert_eq!(policy.retry_delay, Duration::from_secs(1));
+        assert_eq!(policy.max_retry_delay, Duration::from_secs(30));
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(5), None);
+    }
+
+    #[test]
+    fn missing_file() {
+        let error = error(&["--config", "/nonexistent/obs-service.toml"]);
+        assert!(error.starts_with("failed to read config"), "{}", error);
+        assert!(error.contains("/nonexistent/obs-service.toml"), "{}", error);
+    }
+
+    #[test]
+    fn unknown_key_in_file() {
+        let file = ConfigFile::new("unknown", "[video]\nframerate = 30\n");
+        let error = error(&["--config", file.path()]);
+        assert!(error.starts_with("failed to parse config"), "{}", error);
+        assert!(error.contains("framerate"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_file() {
+        let file = ConfigFile::new("invalid", "[video]\nfps = \"fast\"\n");
+        let error = error(&["--config", file.path()]);
+        assert!(error.starts_with("failed to parse config"), "{}", error);
+    }
+
+    #[test]
+    fn validators_reject_bad_input() {
+        let cases: &[(&[&str], &str)] = &[
+            (&["--graphics-module", ""], "modules.graphics_module"),
+            (&["--platform", "wayland"], "display.platform"),
+            (&["--base-width", "0"], "video.base_width"),
+            (&["--base-height", "721"], "video.base_height"),
+            (&["--output-width", "20000"], "video.output_width"),
+            (&["--fps", "0"], "video.fps"),
+            (&["--fps", "241"], "video.fps"),
+            (
+                &["--audio-samples-per-sec", "22050"],
+                "audio.samples_per_sec",
+            ),
+            (&["--stream-url", "http://example.com"], "rtmp://"),
+            (&["--video-encoder", ""], "video_encoder.id"),
+            (&["--video-bitrate", "0"], "video_encoder.bitrate"),
+            (&["--rate-control", "FAST"], "video_encoder.rate_control"),
+            (&["--preset", "instant"], "video_encoder.preset"),
+            (&["--profile", "extreme"], "video_encoder.profile"),
+            (&["--tune", "loud"], "video_encoder.tune"),
+            (&["--audio-encoder", "libfdk_aac"], "obs-libfdk"),
+            (&["--audio-bitrate", "0"], "audio_encoder.bitrate"),
+            (&["--recording-filename", ""], "recording.filename"),
+            (&["--recording-format", "avi"], "recording.format"),
+            (&["--replay-filename", ""], "replay_buffer.filename"),
+            (&["--replay-format", "avi"], "replay_buffer.format"),
+            (
+                &["--replay-max-time-sec", "0"],
+                "replay_buffer.max_time_sec",
+            ),
+            (&["--replay-max-size-mb", "0"], "replay_buffer.max_size_mb"),
+            (&["--reconnect-delay-sec=-1"], "reconnect.retry_delay_sec"),
+            (
+                &[
+                    "--reconnect-delay-sec",
+                    "10",
+                    "--reconnect-max-delay-sec",
+                    "5",
+                ],
+                "reconnect.max_retry_delay_sec",
+            ),
+            (&["--reconnect-backoff", "0.5"], "reconnect.backoff"),
+            (&["--reconnect-backoff", "20"], "reconnect.backoff"),
+        ];
+        for (args, expected) in cases {
+            let error = error(args);
+            assert!(error.contains(expected), "{:?}: {}", args, error);
+        }
+    }
+
+    #[test]
+    fn file_validators_reject_bad_input() {
+        let cases = &[
+            ("[modules]\npaths = []\n", "modules.paths"),
+            (
+                "[modules]\nrequired = [\"obs-x264\", \"obs-ffmpeg\"]\noptional = [\"obs-x264\"]\n",
+                "both required and optional",
+            ),
+            (
+                "[display]\nxvfb = true\nxvfb_path = \"\"\n",
+                "display.xvfb_path",
+            ),
+            (
+                "[outputs.main]\nurl = \"rtmp://example.com\"\n",
+                "invalid output name",
+            ),
+            (
+                "[outputs.backup]\nurl = \"http://example.com\"\n",
+                "invalid config for output \"backup\"",
+            ),
+            (
+                "[recording.audio_encoder]\nid = \"libfdk_aac\"\n",
+                "recording.audio_encoder",
+            ),
+        ];
+        for (i, (toml, expected)) in cases.iter().enumerate() {
+            let file = ConfigFile::new(&format!("validate-{}", i), toml);
+            let error = error(&["--config", file.path()]);
+            assert!(error.contains(expected), "{:?}: {}", toml, error);
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use crate::types::{OutputEvent, SourceEvent};
+use tokio::sync::broadcast;
+
+/// Events are dropped for clients that fall this far behind.
+pub const FEED_CAPACITY: usize = 256;
+
+/// Anything `WatchEvents` clients are told about.
+#[derive(Clone, Debug)]
+pub enum Event {
+    Source(SourceEvent),
+    Output { name: String, event: OutputEvent },
+}
+
+pub type EventFeed = broadcast::Sender<Event>;
@@ -6,7 +6,44 @@ use std::{
     mem,
     ptr::{null, null_mut},
 };
-use x11::{glx, xlib};
+
+mod calldata;
+mod display;
+mod encoder;
+mod events;
+mod filter;
+mod module;
+mod output;
+mod recording;
+mod replay;
+mod scene;
+mod serialize;
+mod service;
+mod signal;
+mod transition;
+mod volmeter;
+
+pub use crate::serialize::{from_array, from_data, to_array, to_data};
+pub use crate::types::{
+    AudioEncoderSettings, AudioLevels, BoundsType, Crop, DisplaySettings, EncoderKind, EncoderType,
+    MediaState, ModuleInfo, ModulePath, ModuleSettings, ModuleStatus, MonitoringType,
+    OrderMovement, OutputEvent, OutputState, OutputStatus, Platform, RecordingFormat,
+    RecordingSettings, ReplayBufferSettings, ReplaySaved, SessionSettings, SourceEvent, StopCode,
+    StreamDestination, Vec2, VideoEncoderSettings, MAIN_OUTPUT,
+};
+pub use calldata::CallData;
+pub use display::Display;
+pub use encoder::{encoder_types, Encoder};
+pub use events::SourceEvents;
+pub use filter::Filter;
+pub use output::{Output, StreamOutput};
+pub use recording::Recording;
+pub use replay::ReplayBuffer;
+pub use scene::{Scene, SceneItem};
+pub use service::Service;
+pub use signal::SignalConnection;
+pub use transition::{Transition, Transitions};
+pub use volmeter::VolMeter;
 
 #[macro_export]
 macro_rules! cstr {
@@ -15,6 +52,49 @@ macro_rules! cstr {
     };
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataType {
+    Null,
+    String,
+    Int,
+    Double,
+    Bool,
+    Object,
+    Array,
+}
+
+impl DataType {
+    unsafe fn from_item(item: *mut ll::obs_data_item_t) -> Option<DataType> {
+        match ll::obs_data_item_gettype(item) {
+            ll::obs_data_type_OBS_DATA_NULL => Some(DataType::Null),
+            ll::obs_data_type_OBS_DATA_STRING => Some(DataType::String),
+            ll::obs_data_type_OBS_DATA_NUMBER => {
+                if ll::obs_data_item_numtype(item) == ll::obs_data_number_type_OBS_DATA_NUM_DOUBLE {
+                    Some(DataType::Double)
+                } else {
+                    Some(DataType::Int)
+                }
+            }
+            ll::obs_data_type_OBS_DATA_BOOLEAN => Some(DataType::Bool),
+            ll::obs_data_type_OBS_DATA_OBJECT => Some(DataType::Object),
+            ll::obs_data_type_OBS_DATA_ARRAY => Some(DataType::Array),
+            _ => None,
+        }
+    }
+
+    // Integers can always be read back as doubles.
+    fn is_compatible(self, expected: DataType) -> bool {
+        self == expected || (self == DataType::Int && expected == DataType::Double)
+    }
+}
+
+unsafe fn string_from_ptr(s: *const std::os::raw::c_char) -> Result<String> {
+    if s.is_null() {
+        return Ok(String::new());
+    }
+    Ok(CStr::from_ptr(s).to_str()?.into())
+}
+
 pub struct Data {
     data: *mut ll::obs_data_t,
 }
@@ -63,6 +143,14 @@ impl Data {
         Ok(())
     }
 
+    pub fn set_double(&mut self, key: &str, val: f64) -> Result<()> {
+        unsafe {
+            let key = CString::new(key)?;
+            ll::obs_data_set_double(self.data, key.as_ptr(), val);
+        }
+        Ok(())
+    }
+
     pub fn set_bool(&mut self, key: &str, val: bool) -> Result<()> {
         unsafe {
             let key = CString::new(key)?;
@@ -87,7 +175,7 @@ impl Data {
         Ok(())
     }
 
-    pub fn get_array(&mut self, key: &str) -> Result<Array> {
+    pub fn get_array(&self, key: &str) -> Result<Array> {
         unsafe {
             let key = CString::new(key)?;
             let data = ll::obs_data_get_array(self.data, key.as_ptr());
@@ -99,11 +187,209 @@ impl Data {
         }
     }
 
+    pub fn get_object(&self, key: &str) -> Result<Option<Data>> {
+        if !self.check_type(key, DataType::Object)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            let data = ll::obs_data_get_obj(self.data, key.as_ptr());
+            if data.is_null() {
+                Ok(None)
+            } else {
+                Ok(Some(Data::from_raw(data)))
+            }
+        }
+    }
+
+    pub fn get_string(&self, key: &str) -> Result<Option<String>> {
+        if !self.check_type(key, DataType::String)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(string_from_ptr(ll::obs_data_get_string(
+                self.data,
+                key.as_ptr(),
+            ))?))
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Result<Option<i64>> {
+        if !self.check_type(key, DataType::Int)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_int(self.data, key.as_ptr())))
+        }
+    }
+
+    pub fn get_double(&self, key: &str) -> Result<Option<f64>> {
+        if !self.check_type(key, DataType::Double)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_double(self.data, key.as_ptr())))
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Result<Option<bool>> {
+        if !self.check_type(key, DataType::Bool)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_bool(self.data, key.as_ptr())))
+        }
+    }
+
+    pub fn get_default_string(&self, key: &str) -> Result<Option<String>> {
+        if !self.check_default_type(key, DataType::String)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(string_from_ptr(ll::obs_data_get_default_string(
+                self.data,
+                key.as_ptr(),
+            ))?))
+        }
+    }
+
+    pub fn get_default_int(&self, key: &str) -> Result<Option<i64>> {
+        if !self.check_default_type(key, DataType::Int)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_default_int(self.data, key.as_ptr())))
+        }
+    }
+
+    pub fn get_default_double(&self, key: &str) -> Result<Option<f64>> {
+        if !self.check_default_type(key, DataType::Double)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_default_double(
+                self.data,
+                key.as_ptr(),
+            )))
+        }
+    }
+
+    pub fn get_default_bool(&self, key: &str) -> Result<Option<bool>> {
+        if !self.check_default_type(key, DataType::Bool)? {
+            return Ok(None);
+        }
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(Some(ll::obs_data_get_default_bool(self.data, key.as_ptr())))
+        }
+    }
+
+    pub fn has_user_value(&self, key: &str) -> Result<bool> {
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(ll::obs_data_has_user_value(self.data, key.as_ptr()))
+        }
+    }
+
+    pub fn has_default_value(&self, key: &str) -> Result<bool> {
+        unsafe {
+            let key = CString::new(key)?;
+            Ok(ll::obs_data_has_default_value(self.data, key.as_ptr()))
+        }
+    }
+
+    /// Returns the type of the item stored under `key`, or `None` if the key
+    /// does not exist.
+    pub fn get_type(&self, key: &str) -> Result<Option<DataType>> {
+        unsafe {
+            let key = CString::new(key)?;
+            let mut item = ll::obs_data_item_byname(self.data, key.as_ptr());
+            if item.is_null() {
+                return Ok(None);
+            }
+            let data_type = DataType::from_item(item);
+            ll::obs_data_item_release(&mut item as _);
+            Ok(data_type)
+        }
+    }
+
+    // Returns Ok(false) if `key` has no user or default value and an error
+    // if it exists with a type other than `expected`.
+    fn check_type(&self, key: &str, expected: DataType) -> Result<bool> {
+        let has_value = {
+            let key_c = CString::new(key)?;
+            unsafe {
+                ll::obs_data_has_user_value(self.data, key_c.as_ptr())
+                    || ll::obs_data_has_default_value(self.data, key_c.as_ptr())
+            }
+        };
+        if !has_value {
+            return Ok(false);
+        }
+        self.expect_type(key, expected)
+    }
+
+    fn check_default_type(&self, key: &str, expected: DataType) -> Result<bool> {
+        if !self.has_default_value(key)? {
+            return Ok(false);
+        }
+        self.expect_type(key, expected)
+    }
+
+    fn expect_type(&self, key: &str, expected: DataType) -> Result<bool> {
+        match self.get_type(key)? {
+            None => Ok(false),
+            Some(actual) if actual.is_compatible(expected) => Ok(true),
+            Some(actual) => Err(anyhow!(
+                "{:?} is {:?}, expected {:?}",
+                key,
+                actual,
+                expected
+            )),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        unsafe {
+            let json = ll::obs_data_get_json(self.data);
+            if json.is_null() {
+                return Err(anyhow!("failed to convert data to json"));
+            }
+            string_from_ptr(json)
+        }
+    }
+
+    pub fn to_json_pretty(&self) -> Result<String> {
+        unsafe {
+            let json = ll::obs_data_get_json_pretty(self.data);
+            if json.is_null() {
+                return Err(anyhow!("failed to convert data to json"));
+            }
+            string_from_ptr(json)
+        }
+    }
+
     pub fn as_mut_ptr(&mut self) -> *mut ll::obs_data_t {
         self.data
     }
 }
 
+impl Clone for Data {
+    fn clone(&self) -> Self {
+        unsafe {
+            ll::obs_data_addref(self.data);
+        }
+        Data { data: self.data }
+    }
+}
+
 impl Drop for Data {
     fn drop(&mut self) {
         unsafe {
@@ -132,17 +418,116 @@ impl Array {
         Array { data }
     }
 
+    pub fn len(&self) -> usize {
+        unsafe { ll::obs_data_array_count(self.data) as usize }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Result<Data> {
+        if index >= self.len() {
+            return Err(anyhow!(
+                "index {} out of range for array of length {}",
+                index,
+                self.len()
+            ));
+        }
+        unsafe {
+            let data = ll::obs_data_array_item(self.data, index as _);
+            if data.is_null() {
+                Err(anyhow!("array item {} does not exist", index))
+            } else {
+                Ok(Data::from_raw(data))
+            }
+        }
+    }
+
+    pub fn iter(&self) -> ArrayIter<'_> {
+        ArrayIter {
+            array: self,
+            index: 0,
+        }
+    }
+
     pub fn push_back(&mut self, mut data: Data) {
         unsafe {
             ll::obs_data_array_push_back(self.data, data.as_mut_ptr());
         }
     }
 
+    pub fn insert(&mut self, index: usize, mut data: Data) -> Result<()> {
+        if index > self.len() {
+            return Err(anyhow!(
+                "insert index {} out of range for array of length {}",
+                index,
+                self.len()
+            ));
+        }
+        unsafe {
+            ll::obs_data_array_insert(self.data, index as _, data.as_mut_ptr());
+        }
+        Ok(())
+    }
+
+    pub fn erase(&mut self, index: usize) -> Result<()> {
+        if index >= self.len() {
+            return Err(anyhow!(
+                "erase index {} out of range for array of length {}",
+                index,
+                self.len()
+            ));
+        }
+        unsafe {
+            ll::obs_data_array_erase(self.data, index as _);
+        }
+        Ok(())
+    }
+
     pub fn as_mut_ptr(&mut self) -> *mut ll::obs_data_array_t {
         self.data
     }
 }
 
+pub struct ArrayIter<'a> {
+    array: &'a Array,
+    index: usize,
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+    type Item = Data;
+
+    fn next(&mut self) -> Option<Data> {
+        let data = self.array.get(self.index).ok()?;
+        self.index += 1;
+        Some(data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.array.len().saturating_sub(self.index);
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> IntoIterator for &'a Array {
+    type Item = Data;
+    type IntoIter = ArrayIter<'a>;
+
+    fn into_iter(self) -> ArrayIter<'a> {
+        self.iter()
+    }
+}
+
+impl Clone for Array {
+    fn clone(&self) -> Self {
+        unsafe {
+            ll::obs_data_array_addref(self.data);
+        }
+        Array { data: self.data }
+    }
+}
+
 impl Drop for Array {
     fn drop(&mut self) {
         unsafe {
@@ -168,9 +553,37 @@ unsafe extern "C" fn source_callback_handler(
 impl Source {
     pub fn by_name(name: &str) -> Result<Source> {
         let source = unsafe { ll::obs_get_source_by_name(cstr!(name)) };
+        if source.is_null() {
+            return Err(anyhow!("source {:?} does not exist", name));
+        }
         Ok(Source { source })
     }
 
+    /// Creates a public source of type `id`.
+    ///
+    /// The returned handle owns the reference from `obs_source_create`, so the
+    /// source is destroyed once it is dropped and no scene uses the source.
+    pub fn create(id: &str, name: &str, settings: Option<&mut Data>) -> Result<Source> {
+        if !Source::type_exists(id)? {
+            return Err(anyhow!("unknown source type {:?}", id));
+        }
+        let settings = match settings {
+            Some(settings) => settings.as_mut_ptr(),
+            None => null_mut(),
+        };
+        unsafe {
+            let source = ll::obs_source_create(cstr!(id), cstr!(name), settings, null_mut());
+            if source.is_null() {
+                return Err(anyhow!("failed to create source {:?}", name));
+            }
+            Ok(Source { source })
+        }
+    }
+
+    pub fn type_exists(id: &str) -> Result<bool> {
+        unsafe { Ok(!ll::obs_source_get_display_name(cstr!(id)).is_null()) }
+    }
+
     pub fn from_raw_inc(source: *mut ll::obs_source_t) -> Source {
         unsafe {
             ll::obs_source_addref(source);
@@ -178,6 +591,16 @@ impl Source {
         Source { source }
     }
 
+    /// Removes the source from obs and any scenes that use it.  The source is
+    /// destroyed once the remaining handles to it are dropped.
+    pub fn remove(self) {
+        unsafe {
+            if !ll::obs_source_removed(self.source) {
+                ll::obs_source_remove(self.source);
+            }
+        }
+    }
+
     pub fn for_each<F: FnMut(&Source)>(mut callback: F) {
         let mut cb: &mut FnMut(&Source) = &mut callback;
         let cb = &mut cb;
@@ -197,6 +620,17 @@ impl Source {
         }
     }
 
+    /// Returns the source's type id, e.g. `vlc_source`.
+    pub fn get_id(&self) -> Result<String> {
+        unsafe {
+            let id = ll::obs_source_get_id(self.source);
+            if id.is_null() {
+                return Err(anyhow!("failed to get id"));
+            }
+            string_from_ptr(id)
+        }
+    }
+
     pub fn has_video(&self) -> bool {
         let flags = unsafe { ll::obs_source_get_output_flags(self.source) };
 
@@ -215,6 +649,32 @@ impl Source {
         (flags & ll::OBS_SOURCE_COMPOSITE) != 0
     }
 
+    pub fn get_settings(&self) -> Result<Data> {
+        unsafe {
+            let settings = ll::obs_source_get_settings(self.source);
+            if settings.is_null() {
+                Err(anyhow!("failed to get source settings"))
+            } else {
+                Ok(Data::from_raw(settings))
+            }
+        }
+    }
+
+    /// Merges `settings` into the source's existing settings.
+    pub fn update(&mut self, settings: &mut Data) {
+        unsafe {
+            ll::obs_source_update(self.source, settings.as_mut_ptr());
+        }
+    }
+
+    /// Replaces the source's settings with `settings`, dropping any keys it
+    /// does not contain.
+    pub fn reset_settings(&mut self, settings: &mut Data) {
+        unsafe {
+            ll::obs_source_reset_settings(self.source, settings.as_mut_ptr());
+        }
+    }
+
     pub fn get_volume(&self) -> f32 {
         unsafe { ll::obs_source_get_volume(self.source) }
     }
@@ -224,6 +684,87 @@ impl Source {
             ll::obs_source_set_volume(self.source, volume);
         }
     }
+
+    pub fn is_muted(&self) -> bool {
+        unsafe { ll::obs_source_muted(self.source) }
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        unsafe {
+            ll::obs_source_set_muted(self.source, muted);
+        }
+    }
+
+    /// Stereo balance from 0.0 (left) to 1.0 (right).  0.5 is centered.
+    pub fn get_balance(&self) -> f32 {
+        unsafe { ll::obs_source_get_balance_value(self.source) }
+    }
+
+    pub fn set_balance(&mut self, balance: f32) {
+        unsafe {
+            ll::obs_source_set_balance_value(self.source, balance);
+        }
+    }
+
+    /// Audio sync offset in nanoseconds.
+    pub fn get_sync_offset(&self) -> i64 {
+        unsafe { ll::obs_source_get_sync_offset(self.source) }
+    }
+
+    pub fn set_sync_offset(&mut self, offset: i64) {
+        unsafe {
+            ll::obs_source_set_sync_offset(self.source, offset);
+        }
+    }
+
+    /// Bitmask of the audio mixer tracks the source is sent to.
+    pub fn get_audio_mixers(&self) -> u32 {
+        unsafe { ll::obs_source_get_audio_mixers(self.source) }
+    }
+
+    pub fn set_audio_mixers(&mut self, mixers: u32) {
+        unsafe {
+            ll::obs_source_set_audio_mixers(self.source, mixers);
+        }
+    }
+
+    pub fn get_monitoring_type(&self) -> MonitoringType {
+        MonitoringType::from_ll(unsafe { ll::obs_source_get_monitoring_type(self.source) })
+    }
+
+    pub fn set_monitoring_type(&mut self, monitoring_type: MonitoringType) {
+        unsafe {
+            ll::obs_source_set_monitoring_type(self.source, monitoring_type.to_ll());
+        }
+    }
+}
+
+impl MonitoringType {
+    fn from_ll(monitoring_type: ll::obs_monitoring_type) -> MonitoringType {
+        match monitoring_type {
+            ll::obs_monitoring_type_OBS_MONITORING_TYPE_MONITOR_ONLY => MonitoringType::MonitorOnly,
+            ll::obs_monitoring_type_OBS_MONITORING_TYPE_MONITOR_AND_OUTPUT => {
+                MonitoringType::MonitorAndOutput
+            }
+            _ => MonitoringType::None,
+        }
+    }
+
+    fn to_ll(self) -> ll::obs_monitoring_type {
+        match self {
+            MonitoringType::None => ll::obs_monitoring_type_OBS_MONITORING_TYPE_NONE,
+            MonitoringType::MonitorOnly => ll::obs_monitoring_type_OBS_MONITORING_TYPE_MONITOR_ONLY,
+            MonitoringType::MonitorAndOutput => {
+                ll::obs_monitoring_type_OBS_MONITORING_TYPE_MONITOR_AND_OUTPUT
+            }
+        }
+    }
+}
+
+impl PartialEq for Source {
+    fn eq(&self, other: &Source) -> bool {
+        self.source == other.source
+    }
 }
 
 impl Drop for Source {
@@ -234,58 +775,23 @@ impl Drop for Source {
     }
 }
 
-pub struct SessionSettings {
-    pub base_width: u32,
-    pub base_height: u32,
-    pub output_width: u32,
-    pub output_height: u32,
-    pub fps: u32,
-    pub audio_samples_per_sec: u32,
-    pub stream_url: String,
-    pub stream_key: String,
-}
-
 pub struct Session {
-    output: *mut obs::obs_output_t,
-    service: *mut obs::obs_service_t,
-    audio_encoder: *mut obs::obs_encoder_t,
-    video_encoder: *mut obs::obs_encoder_t,
+    // Streamed to in order, all sharing the same encoders.
+    outputs: Vec<StreamOutput>,
+    recording: Recording,
+    replay_buffer: ReplayBuffer,
+    audio_encoder: Encoder,
+    video_encoder: Encoder,
+    transitions: Option<Transitions>,
+    modules: Vec<ModuleStatus>,
+    // Dropped last, the Xvfb behind it has to outlive everything else.
+    _display: Display,
 }
 
 impl Session {
     pub fn new(settings: &SessionSettings) -> Result<Session> {
-        let display_env = std::env::var("DISPLAY")?;
+        let display = Display::open(&settings.display, settings.base_width, settings.base_height)?;
         unsafe {
-            let d = xlib::XOpenDisplay(CString::new(display_env)?.as_ptr());
-            let mut a = 0;
-            let mut b = 0;
-
-            let ret = glx::glXQueryExtension(d, &mut a as _, &mut b as _);
-            if ret == 0 {
-                return Err(anyhow!("query extension failed"));
-            }
-            if false {
-                let mut opts = vec![
-                    glx::GLX_RGBA,
-                    glx::GLX_RED_SIZE,
-                    1,
-                    glx::GLX_GREEN_SIZE,
-                    1,
-                    glx::GLX_BLUE_SIZE,
-                    1,
-                    glx::GLX_DEPTH_SIZE,
-                    12,
-                    glx::GLX_DOUBLEBUFFER,
-                    0,
-                ];
-
-                let visual = glx::glXChooseVisual(d, xlib::XDefaultScreen(d), opts.as_mut_ptr());
-                if visual.is_null() {
-                    return Err(anyhow!("can't choose visual"));
-                }
-            }
-
-            obs::obs_set_nix_platform_display(d as *mut c_void);
             let ret = obs::obs_startup(CString::new("en-US")?.as_ptr(), null(), null_mut());
             if !ret {
                 return Err(anyhow!("obs_startup failed"));
@@ -295,10 +801,10 @@ impl Session {
                 return Err(anyhow!("obs not initialized after startup"));
             }
 
-            let module = CString::new("/usr/lib/libobs-opengl.so.0.0")?;
+            let graphics_module = CString::new(settings.graphics_module.as_str())?;
             let mut video_info = obs::obs_video_info {
                 adapter: 0,
-                graphics_module: module.as_ptr(),
+                graphics_module: graphics_module.as_ptr(),
                 output_format: obs::video_format_VIDEO_FORMAT_NV12,
                 fps_num: settings.fps,
                 fps_den: 1,
@@ -313,6 +819,14 @@ impl Session {
             };
 
             let err = obs::obs_reset_video((&mut video_info) as _);
+            if err == obs::OBS_VIDEO_NOT_SUPPORTED as i32 || err == obs::OBS_VIDEO_FAIL as i32 {
+                return Err(anyhow!(
+                    "failed to start {} with the {} platform: error {}",
+                    settings.graphics_module,
+                    settings.display.platform,
+                    err
+                ));
+            }
             if err != obs::OBS_VIDEO_SUCCESS as i32 {
                 return Err(anyhow!("obs reset video failed: {}", err));
             }
@@ -326,99 +840,36 @@ impl Session {
                 return Err(anyhow!("obs reset audio failed: {}", err));
             }
 
-            load_module("image-source")?;
-            load_module("obs-ffmpeg")?;
-            load_module("obs-transitions")?;
-            load_module("rtmp-services")?;
-            load_module("obs-x264")?;
-            load_module("obs-libfdk")?;
-            load_module("obs-outputs")?;
-            load_module("vlc-video")?;
-            load_module("obs-browser")?;
+            let modules = module::load_modules(&settings.modules)?;
 
             obs::obs_post_load_modules();
 
-            let service = obs::obs_service_create(
-                CString::new("rtmp_common")?.as_ptr(),
-                CString::new("rtmp service")?.as_ptr(),
-                null_mut(),
-                null_mut(),
-            );
-
-            if service.is_null() {
-                return Err(anyhow!("create service failed"));
-            }
-
-            let mut rtmp_settings = Data::new()?;
-            rtmp_settings.set_string("server", &settings.stream_url)?;
-            rtmp_settings.set_string("key", &settings.stream_key)?;
-            obs::obs_service_update(service, rtmp_settings.as_mut_ptr());
-
-            let output = obs::obs_output_create(
-                CString::new("rtmp_output")?.as_ptr(),
-                CString::new("RTMP output")?.as_ptr(),
-                null_mut(),
-                null_mut(),
-            );
-            if output.is_null() {
-                return Err(anyhow!("create output failed"));
-            }
-
-            let audio_encoder = obs::obs_audio_encoder_create(
-                cstr!("libfdk_aac"),
-                cstr!("aac enc"),
-                null_mut(),
-                0,
-                null_mut(),
-            );
-            if audio_encoder.is_null() {
-                return Err(anyhow!("failed to create audio encoder"));
-            }
-
-            let settings = obs::obs_encoder_get_settings(audio_encoder);
-            if settings.is_null() {
-                return Err(anyhow!("failed to get encoder settings"));
-            }
-            let mut settings = Data::from_raw(settings);
-            settings.set_int("bitrate", 48000)?;
-            settings.set_bool("afterburner", true)?;
-            obs::obs_encoder_update(audio_encoder, settings.as_mut_ptr());
-
-            let video_encoder = obs::obs_video_encoder_create(
-                cstr!("obs_x264"),
-                cstr!("h264 enc"),
-                null_mut(),
-                null_mut(),
-            );
-            if video_encoder.is_null() {
-                return Err(anyhow!("failed to create video encoder"));
-            }
-
-            let settings = obs::obs_encoder_get_settings(video_encoder);
-            if settings.is_null() {
-                return Err(anyhow!("failed to get video settings"));
-            }
-            let mut settings = Data::from_raw(settings);
-            settings.set_int("bitrate", 2000)?;
-            settings.set_int("keyint_sec", 2)?;
-            settings.set_string("rate_control", "CBR")?;
-
-            // These are settings for the sw decoder
-            settings.set_int("width", video_info.output_width as i64)?;
-            settings.set_int("height", video_info.output_height as i64)?;
-            settings.set_int("fps_num", video_info.fps_num as i64)?;
-            settings.set_int("fps_den", video_info.fps_den as i64)?;
-            settings.set_string("preset", "ultrafast")?;
-            settings.set_string("profile", "main")?;
-            settings.set_string("tune", "zerolatency")?;
-            settings.set_string("x264opts", "")?;
-            obs::obs_encoder_update(video_encoder, settings.as_mut_ptr());
+            let output = StreamOutput::create(MAIN_OUTPUT, settings.stream_destination.clone())?;
+
+            let mut audio_encoder = Encoder::create_audio(&settings.audio_encoder, "aac enc")?;
+            let mut video_encoder =
+                Encoder::create_video(&settings.video_encoder, "h264 enc", &video_info)?;
+            let recording = Recording::create(
+                &settings.recording,
+                &mut video_encoder,
+                &mut audio_encoder,
+                &video_info,
+            )?;
+            let replay_buffer = ReplayBuffer::create(
+                &settings.replay_buffer,
+                &mut video_encoder,
+                &mut audio_encoder,
+            )?;
 
             Ok(Session {
-                output,
-                service,
+                outputs: vec![output],
+                recording,
+                replay_buffer,
                 audio_encoder,
                 video_encoder,
+                transitions: None,
+                modules,
+                _display: display,
             })
         }
     }
@@ -431,28 +882,102 @@ impl Session {
             let files = obs::obs_missing_files_create();
             obs::obs_load_sources(sources.as_mut_ptr(), Some(sources_cb), files as _);
 
-            let scene = obs::obs_get_source_by_name(cstr!("restream"));
-            if scene.is_null() {
-                return Err(anyhow!("can't get restream source"));
+            let scene_name = data
+                .get_string("current_program_scene")?
+                .unwrap_or_else(|| "restream".into());
+            let scene = Source::by_name(&scene_name)?;
+            let transitions = Transitions::load(&data, &scene)?;
+            self.transitions = Some(transitions);
+
+            for output in &mut self.outputs {
+                output.set_encoders(&mut self.video_encoder, &mut self.audio_encoder);
             }
-            obs::obs_set_output_source(0, scene);
-            obs::obs_encoder_set_video(self.video_encoder, obs::obs_get_video());
-            obs::obs_encoder_set_audio(self.audio_encoder, obs::obs_get_audio());
-            obs::obs_output_set_video_encoder(self.output, self.video_encoder);
-            obs::obs_output_set_audio_encoder(self.output, self.audio_encoder, 0);
-            obs::obs_output_set_service(self.output, self.service);
         }
 
         Ok(())
     }
 
-    pub fn start(self) -> Result<()> {
-        unsafe {
-            if obs::obs_output_start(self.output) != true {
-                return Err(anyhow!("output start failed"));
-            }
+    /// Returns the transitions on output channel 0.  Only available once a
+    /// config has been loaded.
+    pub fn transitions(&self) -> Result<&Transitions> {
+        self.transitions
+            .as_ref()
+            .ok_or_else(|| anyhow!("no config loaded"))
+    }
+
+    pub fn transitions_mut(&mut self) -> Result<&mut Transitions> {
+        self.transitions
+            .as_mut()
+            .ok_or_else(|| anyhow!("no config loaded"))
+    }
+
+    /// Returns the requested and discovered modules.
+    pub fn modules(&self) -> &[ModuleStatus] {
+        &self.modules
+    }
+
+    pub fn outputs(&self) -> &[StreamOutput] {
+        &self.outputs
+    }
+
+    pub fn has_output(&self, name: &str) -> bool {
+        self.outputs.iter().any(|output| output.get_name() == name)
+    }
+
+    pub fn output(&self, name: &str) -> Result<&StreamOutput> {
+        self.outputs
+            .iter()
+            .find(|output| output.get_name() == name)
+            .ok_or_else(|| anyhow!("output {:?} does not exist", name))
+    }
+
+    pub fn output_mut(&mut self, name: &str) -> Result<&mut StreamOutput> {
+        self.outputs
+            .iter_mut()
+            .find(|output| output.get_name() == name)
+            .ok_or_else(|| anyhow!("output {:?} does not exist", name))
+    }
+
+    /// Adds a stopped output using the session's encoders.
+    pub fn add_output(
+        &mut self,
+        name: &str,
+        destination: StreamDestination,
+    ) -> Result<&mut StreamOutput> {
+        if self.has_output(name) {
+            return Err(anyhow!("output {:?} already exists", name));
         }
+        let mut output = StreamOutput::create(name, destination)?;
+        output.set_encoders(&mut self.video_encoder, &mut self.audio_encoder);
+        self.outputs.push(output);
+        Ok(self.outputs.last_mut().unwrap())
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    pub fn recording_mut(&mut self) -> &mut Recording {
+        &mut self.recording
+    }
+
+    pub fn replay_buffer(&self) -> &ReplayBuffer {
+        &self.replay_buffer
+    }
 
+    pub fn replay_buffer_mut(&mut self) -> &mut ReplayBuffer {
+        &mut self.replay_buffer
+    }
+
+    /// Removes an output, stopping it if it is active.
+    pub fn remove_output(&mut self, name: &str) -> Result<()> {
+        let index = self
+            .outputs
+            .iter()
+            .position(|output| output.get_name() == name)
+            .ok_or_else(|| anyhow!("output {:?} does not exist", name))?;
+        // Dropping the output force stops it.
+        self.outputs.remove(index);
         Ok(())
     }
 }
@@ -466,26 +991,3 @@ unsafe extern "C" fn sources_cb(param: *mut c_void, source: *mut obs::obs_source
     obs::obs_missing_files_append(f, sf);
     obs::obs_missing_files_destroy(sf);
 }
-
-unsafe fn load_module(module: &str) -> Result<()> {
-    let bin_path = CString::new(format!("/usr/lib/obs-plugins/{}.so", module))?;
-    let data_path = CString::new(format!("/usr/share/obs/obs-plugins/{}", module))?;
-    let mut module = null_mut();
-    let ret = obs::obs_open_module(&mut module as _, bin_path.as_ptr(), data_path.as_ptr());
-    if ret != obs::MODULE_SUCCESS as _ {
-        return Err(anyhow!(
-            "unable to open obs module {:?}:{:?}",
-            bin_path,
-            data_path
-        ));
-    }
-
-    if !obs::obs_init_module(module) {
-        return Err(anyhow!(
-            "unable to open init module {:?}:{:?}",
-            bin_path,
-            data_path
-        ));
-    }
-    Ok(())
-}
@@ -0,0 +1,100 @@
+use super::{ll, string_from_ptr};
+use anyhow::Result;
+use std::{
+    ffi::{c_void, CString},
+    mem,
+    os::raw::c_char,
+    ptr::{null, null_mut},
+};
+
+/// Parameters and return values of procs and signals.
+// Transparent so signal parameters owned by libobs can be borrowed as one.
+#[repr(transparent)]
+pub struct CallData {
+    data: ll::calldata_t,
+}
+
+impl CallData {
+    // calldata_init and calldata_free are inline in the libobs headers.
+    pub fn new() -> CallData {
+        CallData {
+            data: ll::calldata_t {
+                stack: null_mut(),
+                size: 0,
+                capacity: 0,
+                fixed: false,
+            },
+        }
+    }
+
+    /// Borrows parameters owned by libobs, e.g. in a signal callback.
+    ///
+    /// # Safety
+    ///
+    /// `data` must be valid for `'a`.
+    pub unsafe fn from_ptr<'a>(data: *const ll::calldata_t) -> &'a CallData {
+        &*(data as *const CallData)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut ll::calldata_t {
+        &mut self.data as _
+    }
+
+    /// Returns None if there is no string parameter `name`.
+    pub fn get_string(&self, name: &str) -> Result<Option<String>> {
+        let name = CString::new(name)?;
+        let mut s: *const c_char = null();
+        let found = unsafe { ll::calldata_get_string(&self.data as _, name.as_ptr(), &mut s as _) };
+        if !found || s.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(unsafe { string_from_ptr(s)? }))
+    }
+
+    /// Returns None if there is no integer parameter `name`.
+    pub fn get_int(&self, name: &str) -> Result<Option<i64>> {
+        let mut value: i64 = 0;
+        Ok(self.get_data(name, &mut value)?.then(|| value))
+    }
+
+    /// Returns None if there is no float parameter `name`.
+    pub fn get_float(&self, name: &str) -> Result<Option<f64>> {
+        let mut value: f64 = 0.0;
+        Ok(self.get_data(name, &mut value)?.then(|| value))
+    }
+
+    /// Returns None if there is no bool parameter `name`.
+    pub fn get_bool(&self, name: &str) -> Result<Option<bool>> {
+        let mut value = false;
+        Ok(self.get_data(name, &mut value)?.then(|| value))
+    }
+
+    /// Returns None if there is no pointer parameter `name`.
+    pub fn get_ptr<T>(&self, name: &str) -> Result<Option<*mut T>> {
+        let mut value: *mut T = null_mut();
+        Ok(self.get_data(name, &mut value)?.then(|| value))
+    }
+
+    // calldata_get_int and friends are inline in the libobs headers.
+    fn get_data<T: Copy>(&self, name: &str, value: &mut T) -> Result<bool> {
+        let name = CString::new(name)?;
+        Ok(unsafe {
+            ll::calldata_get_data(
+                &self.data as _,
+                name.as_ptr(),
+                value as *mut T as *mut c_void,
+                mem::size_of::<T>() as _,
+            )
+        })
+    }
+}
+
+impl Drop for CallData {
+    fn drop(&mut self) {
+        if !self.data.fixed {
+            unsafe {
+                ll::bfree(self.data.stack as *mut c_void);
+            }
+        }
+    }
+}
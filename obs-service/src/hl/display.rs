@@ -0,0 +1,146 @@
+use super::{ll, DisplaySettings, Platform};
+use anyhow::{anyhow, Context, Result};
+use std::{
+    ffi::{c_void, CString},
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+use x11::{glx, xlib};
+
+impl Platform {
+    fn to_ll(self) -> ll::obs_nix_platform_type {
+        match self {
+            Platform::Glx => ll::obs_nix_platform_type_OBS_NIX_PLATFORM_X11_GLX,
+            Platform::Egl => ll::obs_nix_platform_type_OBS_NIX_PLATFORM_X11_EGL,
+        }
+    }
+}
+
+/// The X display libobs renders with, and the Xvfb server providing it if
+/// one was started.
+pub struct Display {
+    display: *mut xlib::Display,
+    name: String,
+    xvfb: Option<Child>,
+}
+
+impl Display {
+    /// Opens the display from `settings`, starting Xvfb with a `width` by
+    /// `height` screen if needed.
+    pub fn open(settings: &DisplaySettings, width: u32, height: u32) -> Result<Display> {
+        let name = settings
+            .display
+            .clone()
+            .or_else(|| std::env::var("DISPLAY").ok())
+            .filter(|name| !name.is_empty());
+        let (name, xvfb) = match name {
+            Some(name) => (name, None),
+            None if settings.xvfb => {
+                let (name, xvfb) = start_xvfb(&settings.xvfb_path, width, height)?;
+                (name, Some(xvfb))
+            }
+            None => {
+                return Err(anyhow!(
+                    "no X display: DISPLAY is not set.  libobs needs an X server to \
+                     render, even headless; set display.xvfb to start one"
+                ))
+            }
+        };
+
+        let mut display = Display {
+            display: unsafe { xlib::XOpenDisplay(CString::new(name.as_str())?.as_ptr()) },
+            name,
+            xvfb,
+        };
+        if display.display.is_null() {
+            return Err(anyhow!("can't open X display {:?}", display.name));
+        }
+        if settings.platform == Platform::Glx {
+            let mut error_base = 0;
+            let mut event_base = 0;
+            let ret = unsafe {
+                glx::glXQueryExtension(display.display, &mut error_base, &mut event_base)
+            };
+            if ret == 0 {
+                unsafe {
+                    xlib::XCloseDisplay(display.display);
+                }
+                return Err(anyhow!(
+                    "X display {:?} has no GLX extension, try the egl platform",
+                    display.name
+                ));
+            }
+        }
+        println!(
+            "rendering with {} on X display {}",
+            settings.platform, display.name
+        );
+
+        unsafe {
+            ll::obs_set_nix_platform(settings.platform.to_ll());
+            ll::obs_set_nix_platform_display(display.as_mut_ptr());
+        }
+        Ok(display)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut c_void {
+        self.display as *mut c_void
+    }
+}
+
+impl Drop for Display {
+    fn drop(&mut self) {
+        // The X connection is left open, libobs may still use it until the
+        // process exits.  Xvfb would outlive us otherwise.
+        if let Some(xvfb) = self.xvfb.as_mut() {
+            let _ = xvfb.kill();
+            let _ = xvfb.wait();
+        }
+    }
+}
+
+// How long Xvfb gets to report its display number.
+const XVFB_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Starts Xvfb on the first free display number and returns the display name
+// once the server accepts connections.
+fn start_xvfb(path: &str, width: u32, height: u32) -> Result<(String, Child)> {
+    let mut xvfb = Command::new(path)
+        // Xvfb writes the display number it picked to stdout when ready.
+        .args(["-displayfd", "1", "-nolisten", "tcp", "-screen", "0"])
+        .arg(format!("{}x{}x24", width, height))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to start {:?}, is Xvfb installed?", path))?;
+
+    // Read on another thread so a hanging Xvfb can't block startup.  The
+    // thread ends once the child is killed and its stdout closes.
+    let stdout = xvfb.stdout.take().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut number = String::new();
+        let read = BufReader::new(stdout).read_line(&mut number);
+        let _ = sender.send(read.map(|_| number));
+    });
+    match receiver.recv_timeout(XVFB_TIMEOUT) {
+        Ok(Ok(number)) if !number.trim().is_empty() => {
+            let name = format!(":{}", number.trim());
+            println!("started Xvfb on display {}", name);
+            Ok((name, xvfb))
+        }
+        Err(RecvTimeoutError::Timeout) => {
+            let _ = xvfb.kill();
+            let _ = xvfb.wait();
+            Err(anyhow!("Xvfb wasn't ready after {:?}", XVFB_TIMEOUT))
+        }
+        _ => {
+            let _ = xvfb.kill();
+            let status = xvfb.wait()?;
+            Err(anyhow!("Xvfb exited before it was ready: {}", status))
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use super::{
+    ll, string_from_ptr, AudioEncoderSettings, Data, EncoderKind, EncoderType, VideoEncoderSettings,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::CString,
+    os::raw::c_char,
+    ptr::{null, null_mut},
+};
+
+/// Returns every registered encoder type.  Only complete once the modules
+/// are loaded.
+pub fn encoder_types() -> Result<Vec<EncoderType>> {
+    let mut types = Vec::new();
+    let mut idx = 0;
+    loop {
+        let mut id: *const c_char = null();
+        if !unsafe { ll::obs_enum_encoder_types(idx, &mut id as _) } {
+            break;
+        }
+        idx += 1;
+        unsafe {
+            let kind = match ll::obs_get_encoder_type(id) {
+                ll::obs_encoder_type_OBS_ENCODER_AUDIO => EncoderKind::Audio,
+                _ => EncoderKind::Video,
+            };
+            types.push(EncoderType {
+                id: string_from_ptr(id)?,
+                name: string_from_ptr(ll::obs_encoder_get_display_name(id))?,
+                codec: string_from_ptr(ll::obs_get_encoder_codec(id))?,
+                kind,
+            });
+        }
+    }
+    Ok(types)
+}
+
+/// Creates an encoder of type `id`.  Fails with the available encoders of
+/// the same kind if `id` is unknown.
+fn create_encoder(kind: EncoderKind, id: &str, name: &str) -> Result<*mut ll::obs_encoder_t> {
+    let types = encoder_types()?;
+    if !types.iter().any(|t| t.kind == kind && t.id == id) {
+        let available: Vec<&str> = types
+            .iter()
+            .filter(|t| t.kind == kind)
+            .map(|t| t.id.as_str())
+            .collect();
+        let kind_name = match kind {
+            EncoderKind::Audio => "audio",
+            EncoderKind::Video => "video",
+        };
+        return Err(anyhow!(
+            "unknown {} encoder {:?}, available: {}",
+            kind_name,
+            id,
+            available.join(", ")
+        ));
+    }
+
+    let id_c = CString::new(id)?;
+    let name_c = CString::new(name)?;
+    let encoder = unsafe {
+        match kind {
+            EncoderKind::Audio => ll::obs_audio_encoder_create(
+                id_c.as_ptr(),
+                name_c.as_ptr(),
+                null_mut(),
+                0,
+                null_mut(),
+            ),
+            EncoderKind::Video => {
+                ll::obs_video_encoder_create(id_c.as_ptr(), name_c.as_ptr(), null_mut(), null_mut())
+            }
+        }
+    };
+    if encoder.is_null() {
+        return Err(anyhow!("failed to create encoder {:?}", id));
+    }
+    Ok(encoder)
+}
+
+pub struct Encoder {
+    encoder: *mut ll::obs_encoder_t,
+}
+
+impl Encoder {
+    /// Creates an audio encoder for the main audio output.
+    pub fn create_audio(settings: &AudioEncoderSettings, name: &str) -> Result<Encoder> {
+        let mut encoder = Encoder {
+            encoder: create_encoder(EncoderKind::Audio, &settings.id, name)?,
+        };
+        let mut data = encoder.get_settings()?;
+        data.set_int("bitrate", settings.bitrate as i64)?;
+        if settings.id == "libfdk_aac" {
+            data.set_bool("afterburner", true)?;
+        }
+        unsafe {
+            ll::obs_encoder_update(encoder.encoder, data.as_mut_ptr());
+            ll::obs_encoder_set_audio(encoder.encoder, ll::obs_get_audio());
+        }
+        Ok(encoder)
+    }
+
+    /// Creates a video encoder for the main video output described by
+    /// `video_info`.
+    pub fn create_video(
+        settings: &VideoEncoderSettings,
+        name: &str,
+        video_info: &ll::obs_video_info,
+    ) -> Result<Encoder> {
+        let mut encoder = Encoder {
+            encoder: create_encoder(EncoderKind::Video, &settings.id, name)?,
+        };
+        let mut data = encoder.get_settings()?;
+        data.set_int("bitrate", settings.bitrate as i64)?;
+        data.set_int("keyint_sec", settings.keyint_sec as i64)?;
+        data.set_string("rate_control", &settings.rate_control)?;
+
+        // These are settings for the sw decoder
+        data.set_int("width", video_info.output_width as i64)?;
+        data.set_int("height", video_info.output_height as i64)?;
+        data.set_int("fps_num", video_info.fps_num as i64)?;
+        data.set_int("fps_den", video_info.fps_den as i64)?;
+
+        // Empty values keep the encoder's defaults.  Not every encoder takes
+        // these as strings.
+        for (name, value) in &[
+            ("preset", &settings.preset),
+            ("profile", &settings.profile),
+            ("tune", &settings.tune),
+            ("x264opts", &settings.x264opts),
+        ] {
+            if !value.is_empty() {
+                data.set_string(name, value)?;
+            }
+        }
+        unsafe {
+            ll::obs_encoder_update(encoder.encoder, data.as_mut_ptr());
+            ll::obs_encoder_set_video(encoder.encoder, ll::obs_get_video());
+        }
+        Ok(encoder)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut ll::obs_encoder_t {
+        self.encoder
+    }
+
+    fn get_settings(&mut self) -> Result<Data> {
+        let settings = unsafe { ll::obs_encoder_get_settings(self.encoder) };
+        if settings.is_null() {
+            return Err(anyhow!("failed to get encoder settings"));
+        }
+        Ok(Data::from_raw(settings))
+    }
+}
+
+impl Drop for Encoder {
+    fn drop(&mut self) {
+        unsafe {
+            ll::obs_encoder_release(self.encoder);
+        }
+    }
+}
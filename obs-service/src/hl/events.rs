@@ -0,0 +1,201 @@
+use super::{ll, string_from_ptr, CallData, MediaState, SignalConnection, Source, SourceEvent};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Per-source signals and the media state they mean.  `mute` is handled
+// separately.
+const MEDIA_SIGNALS: &[(&str, MediaState)] = &[
+    ("media_play", MediaState::Playing),
+    ("media_started", MediaState::Playing),
+    ("media_restart", MediaState::Playing),
+    ("media_pause", MediaState::Paused),
+    ("media_stopped", MediaState::Stopped),
+    ("media_ended", MediaState::Ended),
+];
+
+type Callback = Box<dyn FnMut(SourceEvent) + Send>;
+
+struct Shared {
+    callback: Mutex<Callback>,
+    // Per-source connections by source pointer, dropped on `source_destroy`
+    // while the source's signal handler is still alive.
+    sources: Mutex<HashMap<usize, Vec<SignalConnection>>>,
+}
+
+impl Shared {
+    fn send(&self, event: SourceEvent) {
+        (self.callback.lock().unwrap())(event)
+    }
+
+    fn watch_source(self: &Arc<Shared>, source: *mut ll::obs_source_t) -> Result<()> {
+        let handler = unsafe { ll::obs_source_get_signal_handler(source) };
+        let mut connections = Vec::new();
+
+        let shared = self.clone();
+        connections.push(SignalConnection::connect(handler, "mute", move |data| {
+            if let (Some(source), Some(muted)) =
+                (source_name(data), data.get_bool("muted").ok().flatten())
+            {
+                shared.send(SourceEvent::MuteChanged { source, muted });
+            }
+        })?);
+        for &(signal, state) in MEDIA_SIGNALS {
+            let shared = self.clone();
+            connections.push(SignalConnection::connect(handler, signal, move |data| {
+                if let Some(source) = source_name(data) {
+                    shared.send(SourceEvent::MediaStateChanged { source, state });
+                }
+            })?);
+        }
+        self.sources
+            .lock()
+            .unwrap()
+            .insert(source as usize, connections);
+        Ok(())
+    }
+
+    fn unwatch_source(&self, source: *mut ll::obs_source_t) {
+        let connections = self.sources.lock().unwrap().remove(&(source as usize));
+        drop(connections);
+    }
+}
+
+/// Runs a callback on changes to any public source until dropped.  The
+/// callback is run on libobs threads and must not block.
+pub struct SourceEvents {
+    global: Vec<SignalConnection>,
+    shared: Arc<Shared>,
+}
+
+impl SourceEvents {
+    pub fn watch<F: FnMut(SourceEvent) + Send + 'static>(callback: F) -> Result<SourceEvents> {
+        let shared = Arc::new(Shared {
+            callback: Mutex::new(Box::new(callback)),
+            sources: Mutex::new(HashMap::new()),
+        });
+        let handler = unsafe { ll::obs_get_signal_handler() };
+        let mut global = Vec::new();
+
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_create",
+            move |data| {
+                if let Some(source) = source_ptr(data) {
+                    if let Err(e) = s.watch_source(source) {
+                        println!("failed to watch new source: {}", e);
+                    }
+                    let kind = unsafe { string_from_ptr(ll::obs_source_get_id(source)) };
+                    if let (Some(name), Ok(kind)) = (source_name(data), kind) {
+                        s.send(SourceEvent::Created { source: name, kind });
+                    }
+                }
+            },
+        )?);
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_destroy",
+            move |data| {
+                if let Some(source) = source_ptr(data) {
+                    s.unwatch_source(source);
+                }
+            },
+        )?);
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_remove",
+            move |data| {
+                if let Some(source) = source_name(data) {
+                    s.send(SourceEvent::Removed { source });
+                }
+            },
+        )?);
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_rename",
+            move |data| {
+                let new_name = data.get_string("new_name").ok().flatten();
+                let prev_name = data.get_string("prev_name").ok().flatten();
+                if let (Some(source), Some(previous_name)) = (new_name, prev_name) {
+                    s.send(SourceEvent::Renamed {
+                        source,
+                        previous_name,
+                    });
+                }
+            },
+        )?);
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_volume",
+            move |data| {
+                if let (Some(source), Some(volume)) =
+                    (source_name(data), data.get_float("volume").ok().flatten())
+                {
+                    s.send(SourceEvent::VolumeChanged {
+                        source,
+                        volume: volume as f32,
+                    });
+                }
+            },
+        )?);
+        let s = shared.clone();
+        global.push(SignalConnection::connect(
+            handler,
+            "source_transition_start",
+            move |data| {
+                let transition = match source_ptr(data) {
+                    Some(transition) => transition,
+                    None => return,
+                };
+                // Returns a new reference, released when `scene` is dropped.
+                let scene = unsafe { ll::obs_transition_get_active_source(transition) };
+                if scene.is_null() {
+                    return;
+                }
+                if let Ok(scene) = (Source { source: scene }).get_name() {
+                    s.send(SourceEvent::SceneSwitched { scene });
+                }
+            },
+        )?);
+
+        // Sources that existed before.
+        let mut result = Ok(());
+        Source::for_each(|source| {
+            if result.is_ok() {
+                result = shared.watch_source(source.source);
+            }
+        });
+        result?;
+
+        Ok(SourceEvents { global, shared })
+    }
+}
+
+impl Drop for SourceEvents {
+    fn drop(&mut self) {
+        // No new sources get watched once the global signals are gone.
+        self.global.clear();
+        self.shared.sources.lock().unwrap().clear();
+    }
+}
+
+fn source_ptr(data: &CallData) -> Option<*mut ll::obs_source_t> {
+    data.get_ptr::<ll::obs_source_t>("source")
+        .ok()
+        .flatten()
+        .filter(|source| !source.is_null())
+}
+
+// Reads the name without taking a reference, the source may be in the middle
+// of being created or removed.
+fn source_name(data: &CallData) -> Option<String> {
+    let source = source_ptr(data)?;
+    unsafe { string_from_ptr(ll::obs_source_get_name(source)).ok() }
+}
@@ -0,0 +1,160 @@
+use super::{ll, Data, OrderMovement, Source};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::{c_void, CString},
+    mem,
+    ptr::null_mut,
+};
+
+/// A filter attached to a source.  Filters are private sources, so they can't
+/// be looked up with `Source::by_name`.
+pub struct Filter {
+    source: Source,
+}
+
+unsafe extern "C" fn filter_callback_handler(
+    _parent: *mut ll::obs_source_t,
+    child: *mut ll::obs_source_t,
+    callback: *mut c_void,
+) {
+    let callback: &mut &mut dyn FnMut(&Filter) = mem::transmute(callback);
+    let filter = Filter {
+        source: Source::from_raw_inc(child),
+    };
+    callback(&filter);
+}
+
+impl Filter {
+    pub fn create(kind: &str, name: &str, settings: Option<&mut Data>) -> Result<Filter> {
+        if !Source::type_exists(kind)? {
+            return Err(anyhow!("unknown filter type {:?}", kind));
+        }
+        let settings = match settings {
+            Some(settings) => settings.as_mut_ptr(),
+            None => null_mut(),
+        };
+        let kind_c = CString::new(kind)?;
+        let name_c = CString::new(name)?;
+        let source =
+            unsafe { ll::obs_source_create_private(kind_c.as_ptr(), name_c.as_ptr(), settings) };
+        if source.is_null() {
+            return Err(anyhow!("failed to create filter {:?}", name));
+        }
+        // Any source type can be created as a private source, only filters
+        // can be attached to one.  Released on drop otherwise.
+        let source = Source { source };
+        let source_type = unsafe { ll::obs_source_get_type(source.source) };
+        if source_type != ll::obs_source_type_OBS_SOURCE_TYPE_FILTER {
+            return Err(anyhow!("{:?} is not a filter type", kind));
+        }
+        Ok(Filter { source })
+    }
+
+    pub fn as_source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn as_source_mut(&mut self) -> &mut Source {
+        &mut self.source
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        self.source.get_name()
+    }
+
+    pub fn get_kind(&self) -> Result<String> {
+        self.source.get_id()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        unsafe { ll::obs_source_enabled(self.source.source) }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        unsafe {
+            ll::obs_source_set_enabled(self.source.source, enabled);
+        }
+    }
+}
+
+impl Source {
+    /// Returns the source's filters in the order they are applied.
+    pub fn filters(&self) -> Vec<Filter> {
+        let mut filters = Vec::new();
+        let mut callback = |filter: &Filter| filters.push(filter.clone());
+        let mut cb: &mut dyn FnMut(&Filter) = &mut callback;
+        let cb = &mut cb;
+        unsafe {
+            ll::obs_source_enum_filters(
+                self.source,
+                Some(filter_callback_handler),
+                cb as *mut _ as *mut c_void,
+            );
+        }
+        filters
+    }
+
+    pub fn get_filter(&self, name: &str) -> Result<Filter> {
+        let name_c = CString::new(name)?;
+        let filter = unsafe { ll::obs_source_get_filter_by_name(self.source, name_c.as_ptr()) };
+        if filter.is_null() {
+            return Err(anyhow!(
+                "source {:?} has no filter {:?}",
+                self.get_name()?,
+                name
+            ));
+        }
+        Ok(Filter {
+            source: Source { source: filter },
+        })
+    }
+
+    pub fn add_filter(
+        &mut self,
+        kind: &str,
+        name: &str,
+        settings: Option<&mut Data>,
+    ) -> Result<Filter> {
+        if self.get_filter(name).is_ok() {
+            return Err(anyhow!(
+                "source {:?} already has a filter {:?}",
+                self.get_name()?,
+                name
+            ));
+        }
+        let filter = Filter::create(kind, name, settings)?;
+        unsafe {
+            ll::obs_source_filter_add(self.source, filter.source.source);
+        }
+        Ok(filter)
+    }
+
+    pub fn remove_filter(&mut self, name: &str) -> Result<()> {
+        let filter = self.get_filter(name)?;
+        unsafe {
+            ll::obs_source_filter_remove(self.source, filter.source.source);
+        }
+        Ok(())
+    }
+
+    pub fn set_filter_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        self.get_filter(name)?.set_enabled(enabled);
+        Ok(())
+    }
+
+    pub fn move_filter(&mut self, name: &str, movement: OrderMovement) -> Result<()> {
+        let filter = self.get_filter(name)?;
+        unsafe {
+            ll::obs_source_filter_set_order(self.source, filter.source.source, movement.to_ll());
+        }
+        Ok(())
+    }
+}
+
+impl Clone for Filter {
+    fn clone(&self) -> Self {
+        Filter {
+            source: Source::from_raw_inc(self.source.source),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use super::{ll, string_from_ptr, ModuleInfo, ModulePath, ModuleSettings, ModuleStatus};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::{c_void, CString},
+    mem,
+    path::Path,
+    ptr::null_mut,
+};
+
+unsafe extern "C" fn find_module_callback_handler(
+    param: *mut c_void,
+    info: *const ll::obs_module_info,
+) {
+    let callback: &mut &mut dyn FnMut(&ModuleInfo) = mem::transmute(param);
+    let info = &*info;
+    let bin_path = string_from_ptr(info.bin_path).unwrap_or_default();
+    let data_path = string_from_ptr(info.data_path).unwrap_or_default();
+    let name = match Path::new(&bin_path).file_stem() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return,
+    };
+    callback(&ModuleInfo {
+        name,
+        bin_path,
+        data_path,
+    });
+}
+
+pub fn add_module_path(path: &ModulePath) -> Result<()> {
+    let bin = CString::new(path.bin.as_str())?;
+    let data = CString::new(path.data.as_str())?;
+    unsafe {
+        ll::obs_add_module_path(bin.as_ptr(), data.as_ptr());
+    }
+    Ok(())
+}
+
+/// Returns the modules in the search paths.  When a module is in several
+/// paths, only the first one is returned.
+pub fn find_modules() -> Vec<ModuleInfo> {
+    let mut modules: Vec<ModuleInfo> = Vec::new();
+    let mut callback = |info: &ModuleInfo| {
+        if !modules.iter().any(|m| m.name == info.name) {
+            modules.push(info.clone());
+        }
+    };
+    let mut cb: &mut dyn FnMut(&ModuleInfo) = &mut callback;
+    let cb = &mut cb;
+    unsafe {
+        ll::obs_find_modules(
+            Some(find_module_callback_handler),
+            cb as *mut _ as *mut c_void,
+        );
+    }
+    modules
+}
+
+impl ModuleInfo {
+    pub fn load(&self) -> Result<()> {
+        let bin_path = CString::new(self.bin_path.as_str())?;
+        let data_path = CString::new(self.data_path.as_str())?;
+        let mut module = null_mut();
+        unsafe {
+            let ret = ll::obs_open_module(&mut module as _, bin_path.as_ptr(), data_path.as_ptr());
+            if ret != ll::MODULE_SUCCESS as i32 {
+                return Err(anyhow!(
+                    "unable to open obs module {:?}: error {}",
+                    self.bin_path,
+                    ret
+                ));
+            }
+            if !ll::obs_init_module(module) {
+                return Err(anyhow!("unable to init obs module {:?}", self.bin_path));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds the search paths and loads the modules in `settings`.  Fails if a
+/// required module can't be loaded.  Optional modules that are missing or
+/// fail to load are reported in the returned status.
+pub fn load_modules(settings: &ModuleSettings) -> Result<Vec<ModuleStatus>> {
+    for path in &settings.paths {
+        add_module_path(path)?;
+    }
+    let found = find_modules();
+
+    let mut wanted: Vec<(&str, bool)> = Vec::new();
+    wanted.extend(settings.required.iter().map(|name| (name.as_str(), true)));
+    wanted.extend(settings.optional.iter().map(|name| (name.as_str(), false)));
+    if settings.load_all {
+        for info in &found {
+            wanted.push((&info.name, false));
+        }
+    }
+
+    let mut statuses: Vec<ModuleStatus> = Vec::new();
+    for (name, required) in wanted {
+        if statuses.iter().any(|s| s.name == name) {
+            continue;
+        }
+        let info = found.iter().find(|info| info.name == name).cloned();
+        let result = match &info {
+            Some(info) => info.load(),
+            None => Err(anyhow!("module {:?} not found", name)),
+        };
+        if let Err(e) = &result {
+            if required {
+                return Err(anyhow!("failed to load required module: {}", e));
+            }
+            println!("warning: {}", e);
+        }
+        statuses.push(ModuleStatus {
+            name: name.into(),
+            info,
+            required,
+            loaded: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+
+    // Report the modules that were found but not asked for.
+    for info in found {
+        if !statuses.iter().any(|s| s.name == info.name) {
+            statuses.push(ModuleStatus {
+                name: info.name.clone(),
+                info: Some(info),
+                required: false,
+                loaded: false,
+                error: None,
+            });
+        }
+    }
+
+    Ok(statuses)
+}
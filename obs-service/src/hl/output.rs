@@ -0,0 +1,343 @@
+use super::{
+    ll, string_from_ptr, CallData, Data, Encoder, OutputEvent, OutputState, OutputStatus, Service,
+    SignalConnection, StopCode, StreamDestination,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::CString,
+    ptr::null_mut,
+    sync::{Arc, Mutex},
+    time::Duration,
+    time::Instant,
+};
+
+impl StopCode {
+    fn from_code(code: i64) -> StopCode {
+        match code {
+            c if c == ll::OBS_OUTPUT_SUCCESS as i64 => StopCode::Success,
+            c if c == ll::OBS_OUTPUT_BAD_PATH as i64 => StopCode::BadPath,
+            c if c == ll::OBS_OUTPUT_CONNECT_FAILED as i64 => StopCode::ConnectFailed,
+            c if c == ll::OBS_OUTPUT_INVALID_STREAM as i64 => StopCode::InvalidStream,
+            c if c == ll::OBS_OUTPUT_ERROR as i64 => StopCode::Error,
+            c if c == ll::OBS_OUTPUT_DISCONNECTED as i64 => StopCode::Disconnected,
+            c if c == ll::OBS_OUTPUT_UNSUPPORTED as i64 => StopCode::Unsupported,
+            c if c == ll::OBS_OUTPUT_NO_SPACE as i64 => StopCode::NoSpace,
+            c if c == ll::OBS_OUTPUT_ENCODE_ERROR as i64 => StopCode::EncodeError,
+            c => StopCode::Unknown(c),
+        }
+    }
+}
+
+pub struct Output {
+    output: *mut ll::obs_output_t,
+    // Cleared by the `stop` signal, stopping finishes asynchronously.
+    started: Arc<Mutex<Option<Instant>>>,
+    signals: Vec<SignalConnection>,
+    events: bool,
+}
+
+impl Output {
+    pub fn create(id: &str, name: &str, settings: Option<&mut Data>) -> Result<Output> {
+        let settings = match settings {
+            Some(settings) => settings.as_mut_ptr(),
+            None => null_mut(),
+        };
+        let id_c = CString::new(id)?;
+        let name_c = CString::new(name)?;
+        let output =
+            unsafe { ll::obs_output_create(id_c.as_ptr(), name_c.as_ptr(), settings, null_mut()) };
+        if output.is_null() {
+            return Err(anyhow!("failed to create output {:?}", name));
+        }
+        let mut output = Output {
+            output,
+            started: Arc::new(Mutex::new(None)),
+            signals: Vec::new(),
+            events: false,
+        };
+        let started = output.started.clone();
+        output.connect_signal("stop", move |_| *started.lock().unwrap() = None)?;
+        Ok(output)
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut ll::obs_output_t {
+        self.output
+    }
+
+    pub fn set_encoders(&mut self, video_encoder: &mut Encoder, audio_encoder: &mut Encoder) {
+        unsafe {
+            ll::obs_output_set_video_encoder(self.output, video_encoder.as_mut_ptr());
+            ll::obs_output_set_audio_encoder(self.output, audio_encoder.as_mut_ptr(), 0);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        unsafe { ll::obs_output_active(self.output) }
+    }
+
+    /// Whether `connect_events` was called.
+    pub fn has_events(&self) -> bool {
+        self.events
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        if self.is_active() {
+            return Err(anyhow!("output is already active"));
+        }
+        // Set before starting, a failed connect emits `stop` right away.
+        *self.started.lock().unwrap() = Some(Instant::now());
+        if !unsafe { ll::obs_output_start(self.output) } {
+            *self.started.lock().unwrap() = None;
+            let error = self.get_last_error();
+            return Err(anyhow!(
+                "output start failed: {}",
+                error.as_deref().unwrap_or("unknown error")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Stops the output once its buffered data is sent.
+    pub fn stop(&mut self) -> Result<()> {
+        if !self.is_active() {
+            return Err(anyhow!("output is not active"));
+        }
+        unsafe {
+            ll::obs_output_stop(self.output);
+        }
+        Ok(())
+    }
+
+    /// Stops the output immediately, dropping any buffered data.
+    pub fn force_stop(&mut self) {
+        unsafe {
+            ll::obs_output_force_stop(self.output);
+        }
+    }
+
+    pub fn status(&self) -> OutputStatus {
+        let state = if self.is_active() {
+            OutputState::Active
+        } else {
+            OutputState::Stopped
+        };
+        let started = *self.started.lock().unwrap();
+        let uptime = match (state, started) {
+            (OutputState::Stopped, _) | (_, None) => Duration::default(),
+            (_, Some(started)) => started.elapsed(),
+        };
+        unsafe {
+            OutputStatus {
+                state,
+                uptime,
+                total_bytes: ll::obs_output_get_total_bytes(self.output),
+                total_frames: ll::obs_output_get_total_frames(self.output),
+                dropped_frames: ll::obs_output_get_frames_dropped(self.output),
+                congestion: ll::obs_output_get_congestion(self.output),
+                last_error: self.get_last_error(),
+            }
+        }
+    }
+
+    /// Runs `callback` on every start and stop.  It is called on
+    /// libobs threads, possibly while the output is being started or stopped,
+    /// so it must not block on anything holding the output.
+    pub fn connect_events<F: FnMut(OutputEvent) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) -> Result<()> {
+        let callback = Arc::new(Mutex::new(callback));
+        let signals: [(&str, fn(&CallData) -> OutputEvent); 2] = [
+            ("start", |_| OutputEvent::Started),
+            ("stop", |data| {
+                let code = data.get_int("code").ok().flatten().unwrap_or(0);
+                OutputEvent::Stopped(StopCode::from_code(code))
+            }),
+        ];
+        for (signal, to_event) in signals.iter().copied() {
+            let callback = callback.clone();
+            self.connect_signal(signal, move |data| {
+                (callback.lock().unwrap())(to_event(data))
+            })?;
+        }
+        self.events = true;
+        Ok(())
+    }
+
+    /// Runs `callback` on every emit of `signal`, on the libobs thread
+    /// emitting it.  Disconnected when the output is dropped.
+    pub fn connect_signal<F: FnMut(&CallData) + Send + 'static>(
+        &mut self,
+        signal: &str,
+        callback: F,
+    ) -> Result<()> {
+        let handler = unsafe { ll::obs_output_get_signal_handler(self.output) };
+        self.signals
+            .push(SignalConnection::connect(handler, signal, callback)?);
+        Ok(())
+    }
+
+    /// Calls one of the output's procs, e.g. the replay buffer's `save`.
+    pub fn call_proc(&mut self, name: &str, data: &mut CallData) -> Result<()> {
+        let name_c = CString::new(name)?;
+        unsafe {
+            let handler = ll::obs_output_get_proc_handler(self.output);
+            if !ll::proc_handler_call(handler, name_c.as_ptr(), data.as_mut_ptr()) {
+                return Err(anyhow!("output has no proc {:?}", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn get_last_error(&self) -> Option<String> {
+        let error = unsafe { string_from_ptr(ll::obs_output_get_last_error(self.output)) };
+        match error {
+            Ok(error) if !error.is_empty() => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        // Disconnected first, the signal handler goes away with the output.
+        self.signals.clear();
+        unsafe {
+            if ll::obs_output_active(self.output) {
+                ll::obs_output_force_stop(self.output);
+            }
+            ll::obs_output_release(self.output);
+        }
+    }
+}
+
+/// A named RTMP output and the service it streams to.
+pub struct StreamOutput {
+    name: String,
+    output: Output,
+    service: Service,
+    destination: StreamDestination,
+    /// Waiting to be restarted after it stopped with an error.
+    reconnecting: bool,
+    /// Stopped to change the destination, restarted by `finish_restart`
+    /// once its `stop` signal comes in.
+    restart: bool,
+}
+
+impl StreamOutput {
+    pub fn create(name: &str, destination: StreamDestination) -> Result<StreamOutput> {
+        destination.validate()?;
+        let mut output = Output::create("rtmp_output", name, None)?;
+        let mut service = Service::create(name, &destination)?;
+        unsafe {
+            ll::obs_output_set_service(output.as_mut_ptr(), service.as_mut_ptr());
+            // libobs' reconnect is disabled, reconnect::run restarts outputs
+            // that stopped with an error.  That also covers failed connects
+            // and has a configurable backoff.
+            ll::obs_output_set_reconnect_settings(output.as_mut_ptr(), 0, 0);
+        }
+        Ok(StreamOutput {
+            name: name.into(),
+            output,
+            service,
+            destination,
+            reconnecting: false,
+            restart: false,
+        })
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn set_encoders(
+        &mut self,
+        video_encoder: &mut Encoder,
+        audio_encoder: &mut Encoder,
+    ) {
+        self.output.set_encoders(video_encoder, audio_encoder);
+    }
+
+    pub fn connect_events<F: FnMut(OutputEvent) + Send + 'static>(
+        &mut self,
+        callback: F,
+    ) -> Result<()> {
+        self.output.connect_events(callback)
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.reconnecting = false;
+        self.restart = false;
+        self.output.start()
+    }
+
+    /// Stops the output.  Also cancels a pending reconnect or restart.
+    pub fn stop(&mut self) -> Result<()> {
+        self.restart = false;
+        if self.reconnecting && !self.output.is_active() {
+            self.reconnecting = false;
+            return Ok(());
+        }
+        self.reconnecting = false;
+        self.output.stop()
+    }
+
+    /// Marks the output as waiting for `reconnect`.
+    pub fn set_reconnecting(&mut self, reconnecting: bool) {
+        self.reconnecting = reconnecting;
+    }
+
+    /// Starts the output again after it stopped with an error, staying
+    /// reconnecting until it is connected.
+    pub fn reconnect(&mut self) -> Result<()> {
+        self.output.start()
+    }
+
+    pub fn status(&self) -> OutputStatus {
+        let mut status = self.output.status();
+        if self.reconnecting && status.state == OutputState::Stopped {
+            status.state = OutputState::Reconnecting;
+        }
+        status
+    }
+
+    pub fn destination(&self) -> &StreamDestination {
+        &self.destination
+    }
+
+    /// Sends the stream to `destination`.  An active output is stopped and
+    /// restarted by `finish_restart` once it has stopped, rtmp_output stops
+    /// on its own thread.  A pending reconnect uses the new destination.
+    pub fn set_destination(&mut self, destination: StreamDestination) -> Result<()> {
+        destination.validate()?;
+        let was_active = self.output.is_active();
+        if was_active && !self.output.has_events() {
+            return Err(anyhow!(
+                "{} can't be restarted without its stop signal",
+                self.name
+            ));
+        }
+        let mut service = Service::create(&self.name, &destination)?;
+        if was_active {
+            self.restart = true;
+            self.output.force_stop();
+        }
+        unsafe {
+            ll::obs_output_set_service(self.output.as_mut_ptr(), service.as_mut_ptr());
+        }
+        self.service = service;
+        println!("{} destination set to {:?}", self.name, destination);
+        self.destination = destination;
+        Ok(())
+    }
+
+    /// Starts the output again if it was stopped by `set_destination`.
+    /// Returns false if no restart was pending.
+    pub fn finish_restart(&mut self) -> Result<bool> {
+        if !self.restart {
+            return Ok(false);
+        }
+        self.start()?;
+        Ok(true)
+    }
+}
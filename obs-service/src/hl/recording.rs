@@ -0,0 +1,118 @@
+use super::{
+    ll, string_from_ptr, Data, Encoder, Output, OutputStatus, RecordingFormat, RecordingSettings,
+};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::{c_void, CString},
+    path::{Path, PathBuf},
+};
+
+/// An ffmpeg_muxer output writing to a new file each time it is started.
+pub struct Recording {
+    output: Output,
+    directory: PathBuf,
+    filename_format: String,
+    format: RecordingFormat,
+    path: Option<PathBuf>,
+    // Kept alive while the output uses them.
+    _encoders: Option<(Encoder, Encoder)>,
+}
+
+impl Recording {
+    pub(super) fn create(
+        settings: &RecordingSettings,
+        stream_video_encoder: &mut Encoder,
+        stream_audio_encoder: &mut Encoder,
+        video_info: &ll::obs_video_info,
+    ) -> Result<Recording> {
+        let mut output = Output::create("ffmpeg_muxer", "recording", None)?;
+        let mut encoders = match &settings.encoders {
+            Some((video, audio)) => Some((
+                Encoder::create_video(video, "recording h264 enc", video_info)?,
+                Encoder::create_audio(audio, "recording aac enc")?,
+            )),
+            None => None,
+        };
+        match &mut encoders {
+            Some((video, audio)) => output.set_encoders(video, audio),
+            None => output.set_encoders(stream_video_encoder, stream_audio_encoder),
+        }
+        Ok(Recording {
+            output,
+            directory: settings.directory.clone(),
+            filename_format: settings.filename_format.clone(),
+            format: settings.format,
+            path: None,
+            _encoders: encoders,
+        })
+    }
+
+    /// Starts recording to a new file named after `name` and returns its
+    /// path.
+    pub fn start(&mut self, name: &str) -> Result<PathBuf> {
+        if self.output.is_active() {
+            return Err(anyhow!("recording is already active"));
+        }
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(self.generate_filename(name)?);
+
+        let mut settings = Data::new()?;
+        settings.set_string(
+            "path",
+            path.to_str()
+                .ok_or_else(|| anyhow!("recording path {:?} is not utf-8", path))?,
+        )?;
+        unsafe {
+            ll::obs_output_update(self.output.as_mut_ptr(), settings.as_mut_ptr());
+        }
+        self.output.start()?;
+        println!("recording to {:?}", path);
+        self.path = Some(path.clone());
+        Ok(path)
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.output.stop()
+    }
+
+    pub fn status(&self) -> OutputStatus {
+        self.output.status()
+    }
+
+    /// The file of the current or last recording.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    fn generate_filename(&self, name: &str) -> Result<String> {
+        let format = self
+            .filename_format
+            .replace("{name}", &sanitize_filename(name));
+        let format = CString::new(format)?;
+        let extension = CString::new(self.format.extension())?;
+        unsafe {
+            let filename =
+                ll::os_generate_formatted_filename(extension.as_ptr(), true, format.as_ptr());
+            if filename.is_null() {
+                return Err(anyhow!("failed to generate recording file name"));
+            }
+            let result = string_from_ptr(filename);
+            ll::bfree(filename as *mut c_void);
+            result
+        }
+    }
+}
+
+// Keeps names like "Race 12: Finals/Heat 2" from creating directories or
+// invalid file names.
+fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,130 @@
+use super::{ll, CallData, Data, Encoder, Output, OutputStatus, ReplayBufferSettings, ReplaySaved};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::CString,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
+
+type PendingSave = Arc<Mutex<Option<oneshot::Sender<Result<PathBuf, String>>>>>;
+
+// The replay buffer's output, for its `saved` signal handler.  The handler is
+// disconnected before the output is released.
+struct OutputPtr(*mut ll::obs_output_t);
+
+unsafe impl Send for OutputPtr {}
+
+/// A replay_buffer output keeping the last few seconds of the stream's
+/// encoders in memory.
+pub struct ReplayBuffer {
+    output: Output,
+    directory: PathBuf,
+    pending: PendingSave,
+}
+
+impl ReplayBuffer {
+    pub(super) fn create(
+        settings: &ReplayBufferSettings,
+        video_encoder: &mut Encoder,
+        audio_encoder: &mut Encoder,
+    ) -> Result<ReplayBuffer> {
+        let directory = settings
+            .directory
+            .to_str()
+            .ok_or_else(|| anyhow!("replay directory {:?} is not utf-8", settings.directory))?;
+        let mut data = Data::new()?;
+        data.set_string("directory", directory)?;
+        data.set_string("format", &settings.filename_format)?;
+        data.set_string("extension", settings.format.extension())?;
+        data.set_bool("allow_spaces", true)?;
+        data.set_int("max_time_sec", settings.max_time_sec as i64)?;
+        data.set_int("max_size_mb", settings.max_size_mb as i64)?;
+
+        let mut output = Output::create("replay_buffer", "replay buffer", Some(&mut data))?;
+        output.set_encoders(video_encoder, audio_encoder);
+
+        let pending = PendingSave::default();
+        let saved_output = OutputPtr(output.as_mut_ptr());
+        let saved_pending = pending.clone();
+        output.connect_signal("saved", move |_| {
+            if let Some(sender) = saved_pending.lock().unwrap().take() {
+                let path = match last_replay(saved_output.0) {
+                    Ok(Some(path)) => Ok(path),
+                    Ok(None) => Err("replay buffer saved no file".to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let _ = sender.send(path);
+            }
+        })?;
+        let stop_pending = pending.clone();
+        output.connect_signal("stop", move |_| {
+            if let Some(sender) = stop_pending.lock().unwrap().take() {
+                let _ = sender.send(Err(
+                    "replay buffer stopped before the replay was saved".to_string()
+                ));
+            }
+        })?;
+
+        Ok(ReplayBuffer {
+            output,
+            directory: settings.directory.clone(),
+            pending,
+        })
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        self.output.start()
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.output.stop()
+    }
+
+    pub fn status(&self) -> OutputStatus {
+        self.output.status()
+    }
+
+    /// Whether a replay is being written.  A closed receiver was given up on
+    /// by its caller and doesn't count.
+    pub fn is_saving(&self) -> bool {
+        let pending = self.pending.lock().unwrap();
+        pending.as_ref().map_or(false, |sender| !sender.is_closed())
+    }
+
+    /// Starts writing the buffer to a new file.  libobs ignores saves while
+    /// a replay is being written, so this fails if `is_saving`.
+    pub fn save(&mut self) -> Result<ReplaySaved> {
+        if !self.output.is_active() {
+            return Err(anyhow!("replay buffer is not active"));
+        }
+        if self.is_saving() {
+            return Err(anyhow!("a replay is already being saved"));
+        }
+        let (sender, receiver) = oneshot::channel();
+        // Set first, the file may be written before call_proc returns.
+        *self.pending.lock().unwrap() = Some(sender);
+        if let Err(e) = self.output.call_proc("save", &mut CallData::new()) {
+            self.pending.lock().unwrap().take();
+            return Err(e);
+        }
+        Ok(receiver)
+    }
+}
+
+// The path of the last saved replay.
+fn last_replay(output: *mut ll::obs_output_t) -> Result<Option<PathBuf>> {
+    let name = CString::new("get_last_replay")?;
+    let mut data = CallData::new();
+    unsafe {
+        let handler = ll::obs_output_get_proc_handler(output);
+        if !ll::proc_handler_call(handler, name.as_ptr(), data.as_mut_ptr()) {
+            return Err(anyhow!("replay buffer has no proc get_last_replay"));
+        }
+    }
+    Ok(data
+        .get_string("path")?
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from))
+}
@@ -0,0 +1,339 @@
+use super::{ll, BoundsType, Crop, OrderMovement, Source, Vec2};
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::{c_void, CString},
+    mem,
+};
+
+impl Vec2 {
+    fn from_ll(v: &ll::vec2) -> Vec2 {
+        let [x, y] = unsafe { v.__bindgen_anon_1.ptr };
+        Vec2 { x, y }
+    }
+
+    fn to_ll(self) -> ll::vec2 {
+        ll::vec2 {
+            __bindgen_anon_1: ll::vec2__bindgen_ty_1 {
+                ptr: [self.x, self.y],
+            },
+        }
+    }
+}
+
+impl BoundsType {
+    fn from_ll(bounds_type: ll::obs_bounds_type) -> BoundsType {
+        match bounds_type {
+            ll::obs_bounds_type_OBS_BOUNDS_STRETCH => BoundsType::Stretch,
+            ll::obs_bounds_type_OBS_BOUNDS_SCALE_INNER => BoundsType::ScaleInner,
+            ll::obs_bounds_type_OBS_BOUNDS_SCALE_OUTER => BoundsType::ScaleOuter,
+            ll::obs_bounds_type_OBS_BOUNDS_SCALE_TO_WIDTH => BoundsType::ScaleToWidth,
+            ll::obs_bounds_type_OBS_BOUNDS_SCALE_TO_HEIGHT => BoundsType::ScaleToHeight,
+            ll::obs_bounds_type_OBS_BOUNDS_MAX_ONLY => BoundsType::MaxOnly,
+            _ => BoundsType::None,
+        }
+    }
+
+    fn to_ll(self) -> ll::obs_bounds_type {
+        match self {
+            BoundsType::None => ll::obs_bounds_type_OBS_BOUNDS_NONE,
+            BoundsType::Stretch => ll::obs_bounds_type_OBS_BOUNDS_STRETCH,
+            BoundsType::ScaleInner => ll::obs_bounds_type_OBS_BOUNDS_SCALE_INNER,
+            BoundsType::ScaleOuter => ll::obs_bounds_type_OBS_BOUNDS_SCALE_OUTER,
+            BoundsType::ScaleToWidth => ll::obs_bounds_type_OBS_BOUNDS_SCALE_TO_WIDTH,
+            BoundsType::ScaleToHeight => ll::obs_bounds_type_OBS_BOUNDS_SCALE_TO_HEIGHT,
+            BoundsType::MaxOnly => ll::obs_bounds_type_OBS_BOUNDS_MAX_ONLY,
+        }
+    }
+}
+
+impl OrderMovement {
+    pub(super) fn to_ll(self) -> ll::obs_order_movement {
+        match self {
+            OrderMovement::Up => ll::obs_order_movement_OBS_ORDER_MOVE_UP,
+            OrderMovement::Down => ll::obs_order_movement_OBS_ORDER_MOVE_DOWN,
+            OrderMovement::Top => ll::obs_order_movement_OBS_ORDER_MOVE_TOP,
+            OrderMovement::Bottom => ll::obs_order_movement_OBS_ORDER_MOVE_BOTTOM,
+        }
+    }
+}
+
+/// A scene.  The reference is held through the scene's source.
+pub struct Scene {
+    source: Source,
+    scene: *mut ll::obs_scene_t,
+}
+
+unsafe extern "C" fn scene_item_callback_handler(
+    _scene: *mut ll::obs_scene_t,
+    item: *mut ll::obs_sceneitem_t,
+    callback: *mut c_void,
+) -> bool {
+    let callback: &mut &mut dyn FnMut(&SceneItem) = mem::transmute(callback);
+    let item = SceneItem::from_raw_inc(item);
+    callback(&item);
+    true
+}
+
+impl Scene {
+    pub fn create(name: &str) -> Result<Scene> {
+        let name = CString::new(name)?;
+        unsafe {
+            let scene = ll::obs_scene_create(name.as_ptr());
+            if scene.is_null() {
+                return Err(anyhow!("failed to create scene {:?}", name));
+            }
+            // The reference returned by obs_scene_create is owned by the
+            // scene's source.
+            let source = ll::obs_scene_get_source(scene);
+            Ok(Scene {
+                source: Source { source },
+                scene,
+            })
+        }
+    }
+
+    pub fn by_name(name: &str) -> Result<Scene> {
+        Scene::from_source(Source::by_name(name)?)
+    }
+
+    pub fn from_source(source: Source) -> Result<Scene> {
+        let scene = unsafe { ll::obs_scene_from_source(source.source) };
+        if scene.is_null() {
+            return Err(anyhow!("{:?} is not a scene", source.get_name()?));
+        }
+        Ok(Scene { source, scene })
+    }
+
+    pub fn as_source(&self) -> &Source {
+        &self.source
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        self.source.get_name()
+    }
+
+    /// Returns the scene's items from bottom to top.
+    pub fn items(&self) -> Vec<SceneItem> {
+        let mut items = Vec::new();
+        let mut callback = |item: &SceneItem| items.push(item.clone());
+        let mut cb: &mut dyn FnMut(&SceneItem) = &mut callback;
+        let cb = &mut cb;
+        unsafe {
+            ll::obs_scene_enum_items(
+                self.scene,
+                Some(scene_item_callback_handler),
+                cb as *mut _ as *mut c_void,
+            );
+        }
+        items
+    }
+
+    pub fn find_item(&self, source_name: &str) -> Result<SceneItem> {
+        let name = CString::new(source_name)?;
+        let item = unsafe { ll::obs_scene_find_source(self.scene, name.as_ptr()) };
+        if item.is_null() {
+            return Err(anyhow!(
+                "scene {:?} has no item {:?}",
+                self.get_name()?,
+                source_name
+            ));
+        }
+        Ok(SceneItem::from_raw_inc(item))
+    }
+
+    pub fn find_item_by_id(&self, id: i64) -> Result<SceneItem> {
+        let item = unsafe { ll::obs_scene_find_sceneitem_by_id(self.scene, id) };
+        if item.is_null() {
+            return Err(anyhow!(
+                "scene {:?} has no item with id {}",
+                self.get_name()?,
+                id
+            ));
+        }
+        Ok(SceneItem::from_raw_inc(item))
+    }
+
+    pub fn add(&mut self, source: &Source) -> Result<SceneItem> {
+        let item = unsafe { ll::obs_scene_add(self.scene, source.source) };
+        if item.is_null() {
+            return Err(anyhow!(
+                "failed to add {:?} to scene {:?}",
+                source.get_name()?,
+                self.get_name()?
+            ));
+        }
+        Ok(SceneItem::from_raw_inc(item))
+    }
+}
+
+pub struct SceneItem {
+    item: *mut ll::obs_sceneitem_t,
+}
+
+impl SceneItem {
+    pub fn from_raw_inc(item: *mut ll::obs_sceneitem_t) -> SceneItem {
+        unsafe {
+            ll::obs_sceneitem_addref(item);
+        }
+        SceneItem { item }
+    }
+
+    pub fn get_id(&self) -> i64 {
+        unsafe { ll::obs_sceneitem_get_id(self.item) }
+    }
+
+    pub fn get_source(&self) -> Source {
+        Source::from_raw_inc(unsafe { ll::obs_sceneitem_get_source(self.item) })
+    }
+
+    /// Removes the item from its scene.
+    pub fn remove(self) {
+        unsafe {
+            ll::obs_sceneitem_remove(self.item);
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        unsafe { ll::obs_sceneitem_visible(self.item) }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        unsafe {
+            ll::obs_sceneitem_set_visible(self.item, visible);
+        }
+    }
+
+    pub fn get_pos(&self) -> Vec2 {
+        let mut pos = Vec2::default().to_ll();
+        unsafe {
+            ll::obs_sceneitem_get_pos(self.item, &mut pos as _);
+        }
+        Vec2::from_ll(&pos)
+    }
+
+    pub fn set_pos(&mut self, pos: Vec2) {
+        let pos = pos.to_ll();
+        unsafe {
+            ll::obs_sceneitem_set_pos(self.item, &pos as _);
+        }
+    }
+
+    pub fn get_scale(&self) -> Vec2 {
+        let mut scale = Vec2::default().to_ll();
+        unsafe {
+            ll::obs_sceneitem_get_scale(self.item, &mut scale as _);
+        }
+        Vec2::from_ll(&scale)
+    }
+
+    pub fn set_scale(&mut self, scale: Vec2) {
+        let scale = scale.to_ll();
+        unsafe {
+            ll::obs_sceneitem_set_scale(self.item, &scale as _);
+        }
+    }
+
+    /// Rotation in degrees.
+    pub fn get_rot(&self) -> f32 {
+        unsafe { ll::obs_sceneitem_get_rot(self.item) }
+    }
+
+    pub fn set_rot(&mut self, rot: f32) {
+        unsafe {
+            ll::obs_sceneitem_set_rot(self.item, rot);
+        }
+    }
+
+    pub fn get_crop(&self) -> Crop {
+        let mut crop = ll::obs_sceneitem_crop {
+            left: 0,
+            top: 0,
+            right: 0,
+            bottom: 0,
+        };
+        unsafe {
+            ll::obs_sceneitem_get_crop(self.item, &mut crop as _);
+        }
+        Crop {
+            left: crop.left,
+            top: crop.top,
+            right: crop.right,
+            bottom: crop.bottom,
+        }
+    }
+
+    pub fn set_crop(&mut self, crop: Crop) {
+        let crop = ll::obs_sceneitem_crop {
+            left: crop.left,
+            top: crop.top,
+            right: crop.right,
+            bottom: crop.bottom,
+        };
+        unsafe {
+            ll::obs_sceneitem_set_crop(self.item, &crop as _);
+        }
+    }
+
+    pub fn get_bounds_type(&self) -> BoundsType {
+        BoundsType::from_ll(unsafe { ll::obs_sceneitem_get_bounds_type(self.item) })
+    }
+
+    pub fn set_bounds_type(&mut self, bounds_type: BoundsType) {
+        unsafe {
+            ll::obs_sceneitem_set_bounds_type(self.item, bounds_type.to_ll());
+        }
+    }
+
+    pub fn get_bounds(&self) -> Vec2 {
+        let mut bounds = Vec2::default().to_ll();
+        unsafe {
+            ll::obs_sceneitem_get_bounds(self.item, &mut bounds as _);
+        }
+        Vec2::from_ll(&bounds)
+    }
+
+    pub fn set_bounds(&mut self, bounds: Vec2) {
+        let bounds = bounds.to_ll();
+        unsafe {
+            ll::obs_sceneitem_set_bounds(self.item, &bounds as _);
+        }
+    }
+
+    /// Alignment is a bitmask of the `OBS_ALIGN_*` flags.
+    pub fn get_bounds_alignment(&self) -> u32 {
+        unsafe { ll::obs_sceneitem_get_bounds_alignment(self.item) }
+    }
+
+    pub fn set_bounds_alignment(&mut self, alignment: u32) {
+        unsafe {
+            ll::obs_sceneitem_set_bounds_alignment(self.item, alignment);
+        }
+    }
+
+    pub fn set_order(&mut self, movement: OrderMovement) {
+        unsafe {
+            ll::obs_sceneitem_set_order(self.item, movement.to_ll());
+        }
+    }
+
+    /// Moves the item to `position`, counted from the bottom of the scene.
+    pub fn set_order_position(&mut self, position: i32) {
+        unsafe {
+            ll::obs_sceneitem_set_order_position(self.item, position);
+        }
+    }
+}
+
+impl Clone for SceneItem {
+    fn clone(&self) -> Self {
+        SceneItem::from_raw_inc(self.item)
+    }
+}
+
+impl Drop for SceneItem {
+    fn drop(&mut self) {
+        unsafe {
+            ll::obs_sceneitem_release(self.item);
+        }
+    }
+}
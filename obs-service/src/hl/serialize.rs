@@ -0,0 +1,92 @@
+//! `Data` and `Array` as storage for the serde support in
+//! `crate::serialize`.
+
+use super::{ll, string_from_ptr, Array, Data, DataType};
+use crate::serialize::{ArrayStore, DataStore, DataValue};
+use anyhow::Result;
+
+impl DataStore for Data {
+    type Array = Array;
+
+    fn create() -> Result<Data> {
+        Data::new()
+    }
+
+    fn set(&mut self, key: &str, value: DataValue<Data>) -> Result<()> {
+        match value {
+            DataValue::Null => Ok(()),
+            DataValue::Bool(v) => self.set_bool(key, v),
+            DataValue::Int(v) => self.set_int(key, v),
+            DataValue::Double(v) => self.set_double(key, v),
+            DataValue::String(v) => self.set_string(key, &v),
+            DataValue::Object(v) => self.set_object(key, v),
+            DataValue::Array(v) => self.set_array(key, v),
+        }
+    }
+
+    // Items with only a default value are included, like obs_data_get_*
+    // falls back to the default.
+    fn values(&self) -> Result<Vec<(String, DataValue<Data>)>> {
+        let mut values = Vec::new();
+        unsafe {
+            let mut item = ll::obs_data_first(self.data);
+            while !item.is_null() {
+                let value = string_from_ptr(ll::obs_data_item_get_name(item))
+                    .and_then(|name| Ok((name, item_value(item)?)));
+                match value {
+                    Ok(value) => values.push(value),
+                    Err(e) => {
+                        ll::obs_data_item_release(&mut item as _);
+                        return Err(e);
+                    }
+                }
+                ll::obs_data_item_next(&mut item as _);
+            }
+        }
+        Ok(values)
+    }
+}
+
+unsafe fn item_value(item: *mut ll::obs_data_item_t) -> Result<DataValue<Data>> {
+    Ok(match DataType::from_item(item) {
+        Some(DataType::String) => {
+            DataValue::String(string_from_ptr(ll::obs_data_item_get_string(item))?)
+        }
+        Some(DataType::Int) => DataValue::Int(ll::obs_data_item_get_int(item)),
+        Some(DataType::Double) => DataValue::Double(ll::obs_data_item_get_double(item)),
+        Some(DataType::Bool) => DataValue::Bool(ll::obs_data_item_get_bool(item)),
+        Some(DataType::Object) => {
+            let data = ll::obs_data_item_get_obj(item);
+            if data.is_null() {
+                DataValue::Null
+            } else {
+                DataValue::Object(Data::from_raw(data))
+            }
+        }
+        Some(DataType::Array) => {
+            let array = ll::obs_data_item_get_array(item);
+            if array.is_null() {
+                DataValue::Null
+            } else {
+                DataValue::Array(Array::from_raw(array))
+            }
+        }
+        Some(DataType::Null) | None => DataValue::Null,
+    })
+}
+
+impl ArrayStore for Array {
+    type Data = Data;
+
+    fn create() -> Result<Array> {
+        Array::new()
+    }
+
+    fn push(&mut self, data: Data) {
+        self.push_back(data);
+    }
+
+    fn items(&self) -> Result<Vec<Data>> {
+        (0..self.len()).map(|index| self.get(index)).collect()
+    }
+}
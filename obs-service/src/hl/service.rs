@@ -0,0 +1,58 @@
+use super::{ll, Data, StreamDestination};
+use anyhow::{anyhow, Result};
+use std::{ffi::CString, ptr::null_mut};
+
+impl StreamDestination {
+    fn service_id(&self) -> &'static str {
+        match self.service {
+            Some(_) => "rtmp_common",
+            None => "rtmp_custom",
+        }
+    }
+
+    fn to_data(&self) -> Result<Data> {
+        let mut data = Data::new()?;
+        if let Some(service) = &self.service {
+            data.set_string("service", service)?;
+        }
+        data.set_string("server", &self.server)?;
+        data.set_string("key", &self.key)?;
+        Ok(data)
+    }
+}
+
+pub struct Service {
+    service: *mut ll::obs_service_t,
+}
+
+impl Service {
+    pub fn create(name: &str, destination: &StreamDestination) -> Result<Service> {
+        let id_c = CString::new(destination.service_id())?;
+        let name_c = CString::new(name)?;
+        let mut settings = destination.to_data()?;
+        let service = unsafe {
+            ll::obs_service_create(
+                id_c.as_ptr(),
+                name_c.as_ptr(),
+                settings.as_mut_ptr(),
+                null_mut(),
+            )
+        };
+        if service.is_null() {
+            return Err(anyhow!("failed to create service for {:?}", destination));
+        }
+        Ok(Service { service })
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut ll::obs_service_t {
+        self.service
+    }
+}
+
+impl Drop for Service {
+    fn drop(&mut self) {
+        unsafe {
+            ll::obs_service_release(self.service);
+        }
+    }
+}
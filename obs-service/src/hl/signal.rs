@@ -0,0 +1,70 @@
+use super::{ll, CallData};
+use anyhow::{anyhow, Result};
+use std::{ffi::c_void, ffi::CString};
+
+type Callback = Box<dyn FnMut(&CallData) + Send>;
+
+/// A closure connected to a signal, e.g. an output's `stop`.  It is run on
+/// whichever libobs thread emits the signal and disconnected on drop.
+///
+/// The connection must be dropped before the object owning `handler` is
+/// released.
+pub struct SignalConnection {
+    handler: *mut ll::signal_handler_t,
+    signal: CString,
+    // Boxed so the pointer handed to libobs stays valid if the connection
+    // moves.
+    callback: Box<Callback>,
+}
+
+// signal_handler_t does its own locking and the callback is required to be
+// Send.
+unsafe impl Send for SignalConnection {}
+
+unsafe extern "C" fn signal_callback_handler(param: *mut c_void, data: *mut ll::calldata_t) {
+    let callback = &mut *(param as *mut Callback);
+    callback(CallData::from_ptr(data));
+}
+
+impl SignalConnection {
+    pub(super) fn connect<F: FnMut(&CallData) + Send + 'static>(
+        handler: *mut ll::signal_handler_t,
+        signal: &str,
+        callback: F,
+    ) -> Result<SignalConnection> {
+        if handler.is_null() {
+            return Err(anyhow!("no signal handler for {:?}", signal));
+        }
+        let mut connection = SignalConnection {
+            handler,
+            signal: CString::new(signal)?,
+            callback: Box::new(Box::new(callback)),
+        };
+        unsafe {
+            ll::signal_handler_connect(
+                connection.handler,
+                connection.signal.as_ptr(),
+                Some(signal_callback_handler),
+                connection.callback_ptr(),
+            );
+        }
+        Ok(connection)
+    }
+
+    fn callback_ptr(&mut self) -> *mut c_void {
+        &mut *self.callback as *mut Callback as *mut c_void
+    }
+}
+
+impl Drop for SignalConnection {
+    fn drop(&mut self) {
+        unsafe {
+            ll::signal_handler_disconnect(
+                self.handler,
+                self.signal.as_ptr(),
+                Some(signal_callback_handler),
+                self.callback_ptr(),
+            );
+        }
+    }
+}
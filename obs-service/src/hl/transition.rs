@@ -0,0 +1,197 @@
+use super::{ll, Data, DataType, Source};
+use anyhow::{anyhow, Result};
+use std::{ffi::CString, ptr::null_mut};
+
+// Transitions the obs frontend always provides.  They are not stored in the
+// scene collection's `transitions` list.
+const DEFAULT_TRANSITIONS: &[(&str, &str)] =
+    &[("cut_transition", "Cut"), ("fade_transition", "Fade")];
+const DEFAULT_TRANSITION_DURATION_MS: u32 = 300;
+
+pub struct Transition {
+    source: Source,
+}
+
+impl Transition {
+    pub fn create(id: &str, name: &str, settings: Option<&mut Data>) -> Result<Transition> {
+        let settings = match settings {
+            Some(settings) => settings.as_mut_ptr(),
+            None => null_mut(),
+        };
+        let id_c = CString::new(id)?;
+        let name_c = CString::new(name)?;
+        unsafe {
+            let source = ll::obs_source_create_private(id_c.as_ptr(), name_c.as_ptr(), settings);
+            if source.is_null() {
+                return Err(anyhow!("failed to create transition {:?}", name));
+            }
+            Transition::from_source(Source { source })
+        }
+    }
+
+    /// Loads a transition saved in a scene collection's `transitions` list.
+    pub fn load(data: &mut Data) -> Result<Transition> {
+        let source = unsafe { ll::obs_load_private_source(data.as_mut_ptr()) };
+        if source.is_null() {
+            return Err(anyhow!("failed to load transition"));
+        }
+        Transition::from_source(Source { source })
+    }
+
+    fn from_source(source: Source) -> Result<Transition> {
+        let source_type = unsafe { ll::obs_source_get_type(source.source) };
+        if source_type != ll::obs_source_type_OBS_SOURCE_TYPE_TRANSITION {
+            return Err(anyhow!("{:?} is not a transition", source.get_name()?));
+        }
+        Ok(Transition { source })
+    }
+
+    pub fn get_name(&self) -> Result<String> {
+        self.source.get_name()
+    }
+
+    /// Switches to `scene` immediately.
+    pub fn set(&mut self, scene: &Source) {
+        unsafe {
+            ll::obs_transition_set(self.source.source, scene.source);
+        }
+    }
+
+    /// Starts transitioning to `scene`.  Fails if a transition is already in
+    /// progress.
+    pub fn start(&mut self, scene: &Source, duration_ms: u32) -> Result<()> {
+        let started = unsafe {
+            ll::obs_transition_start(
+                self.source.source,
+                ll::obs_transition_mode_OBS_TRANSITION_MODE_AUTO,
+                duration_ms,
+                scene.source,
+            )
+        };
+        if !started {
+            return Err(anyhow!("transition {:?} is busy", self.get_name()?));
+        }
+        Ok(())
+    }
+
+    /// Returns the scene currently shown, or the scene being transitioned to.
+    pub fn get_active_source(&self) -> Option<Source> {
+        let source = unsafe { ll::obs_transition_get_active_source(self.source.source) };
+        if source.is_null() {
+            None
+        } else {
+            Some(Source { source })
+        }
+    }
+}
+
+/// The transitions available on output channel 0 and the one in use.
+pub struct Transitions {
+    transitions: Vec<Transition>,
+    current: usize,
+    duration_ms: u32,
+}
+
+impl Transitions {
+    /// Creates the default transitions plus the ones in the scene collection
+    /// `config` and puts the current one on output channel 0 showing `scene`.
+    pub fn load(config: &Data, scene: &Source) -> Result<Transitions> {
+        let mut transitions = Vec::new();
+        for (id, name) in DEFAULT_TRANSITIONS {
+            transitions.push(Transition::create(id, name, None)?);
+        }
+        if config.get_type("transitions")? == Some(DataType::Array) {
+            for mut data in &config.get_array("transitions")? {
+                transitions.push(Transition::load(&mut data)?);
+            }
+        }
+
+        let current = match config.get_string("current_transition")? {
+            Some(name) => find_transition(&transitions, &name).unwrap_or(0),
+            None => 0,
+        };
+        let duration_ms = match config.get_int("transition_duration")? {
+            Some(duration) if duration >= 0 => duration as u32,
+            _ => DEFAULT_TRANSITION_DURATION_MS,
+        };
+
+        let mut transitions = Transitions {
+            transitions,
+            current,
+            duration_ms,
+        };
+        let transition = &mut transitions.transitions[current];
+        transition.set(scene);
+        unsafe {
+            ll::obs_set_output_source(0, transition.source.source);
+        }
+        Ok(transitions)
+    }
+
+    pub fn names(&self) -> Result<Vec<String>> {
+        self.transitions.iter().map(Transition::get_name).collect()
+    }
+
+    pub fn current(&self) -> &Transition {
+        &self.transitions[self.current]
+    }
+
+    pub fn duration_ms(&self) -> u32 {
+        self.duration_ms
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        find_transition(&self.transitions, name).is_some()
+    }
+
+    /// Returns the scene on output channel 0.
+    pub fn current_scene(&self) -> Result<Source> {
+        self.current()
+            .get_active_source()
+            .ok_or_else(|| anyhow!("no scene is active"))
+    }
+
+    /// Transitions to `scene`.  `transition` becomes the current transition if
+    /// given and `duration_ms` defaults to the configured duration.  Nothing
+    /// changes if the transition can't be started.
+    pub fn switch_scene(
+        &mut self,
+        scene: &Source,
+        transition: Option<&str>,
+        duration_ms: Option<u32>,
+    ) -> Result<()> {
+        let previous = self.current;
+        if let Some(name) = transition {
+            let index = find_transition(&self.transitions, name)
+                .ok_or_else(|| anyhow!("transition {:?} does not exist", name))?;
+            self.set_current(index);
+        }
+        let duration_ms = duration_ms.unwrap_or(self.duration_ms);
+        let started = self.transitions[self.current].start(scene, duration_ms);
+        if started.is_err() {
+            self.set_current(previous);
+        }
+        started
+    }
+
+    fn set_current(&mut self, index: usize) {
+        if index == self.current {
+            return;
+        }
+        let old = self.transitions[self.current].source.source;
+        let new = self.transitions[index].source.source;
+        // Carry the scene shown by the old transition over to the new one.
+        unsafe {
+            ll::obs_transition_swap_begin(new, old);
+            ll::obs_set_output_source(0, new);
+            ll::obs_transition_swap_end(new, old);
+        }
+        self.current = index;
+    }
+}
+
+fn find_transition(transitions: &[Transition], name: &str) -> Option<usize> {
+    transitions
+        .iter()
+        .position(|t| t.get_name().map(|n| n == name).unwrap_or(false))
+}
@@ -0,0 +1,91 @@
+use super::{ll, AudioLevels, Source};
+use anyhow::{anyhow, Result};
+use std::{ffi::c_void, slice};
+
+type Callback = Box<dyn FnMut(&AudioLevels) + Send>;
+
+struct MeterState {
+    channels: usize,
+    callback: Callback,
+}
+
+/// A volume meter attached to a source.  The callback is run on the libobs
+/// audio thread every time the source produces audio.
+pub struct VolMeter {
+    meter: *mut ll::obs_volmeter_t,
+    // Boxed so the pointer handed to libobs stays valid if the VolMeter moves.
+    state: Box<MeterState>,
+}
+
+// obs_volmeter_t does its own locking and the callback is required to be
+// Send, so the meter can be owned by any thread.
+unsafe impl Send for VolMeter {}
+
+unsafe extern "C" fn volmeter_callback_handler(
+    param: *mut c_void,
+    magnitude: *const f32,
+    peak: *const f32,
+    input_peak: *const f32,
+) {
+    let state = &mut *(param as *mut MeterState);
+    let levels = AudioLevels {
+        magnitude: slice::from_raw_parts(magnitude, state.channels).to_vec(),
+        peak: slice::from_raw_parts(peak, state.channels).to_vec(),
+        input_peak: slice::from_raw_parts(input_peak, state.channels).to_vec(),
+    };
+    (state.callback)(&levels);
+}
+
+impl VolMeter {
+    pub fn new<F: FnMut(&AudioLevels) + Send + 'static>(
+        source: &Source,
+        callback: F,
+    ) -> Result<VolMeter> {
+        unsafe {
+            let meter = ll::obs_volmeter_create(ll::obs_fader_type_OBS_FADER_LOG);
+            if meter.is_null() {
+                return Err(anyhow!("failed to create volume meter"));
+            }
+            let mut meter = VolMeter {
+                meter,
+                state: Box::new(MeterState {
+                    channels: 0,
+                    callback: Box::new(callback),
+                }),
+            };
+            if !ll::obs_volmeter_attach_source(meter.meter, source.source) {
+                return Err(anyhow!(
+                    "failed to attach volume meter to {:?}",
+                    source.get_name()?
+                ));
+            }
+            // Counted from the attached source, it is 1 before that.
+            meter.state.channels = (ll::obs_volmeter_get_nr_channels(meter.meter).max(0) as usize)
+                .min(ll::MAX_AUDIO_CHANNELS as usize);
+            ll::obs_volmeter_add_callback(
+                meter.meter,
+                Some(volmeter_callback_handler),
+                meter.state_ptr(),
+            );
+            Ok(meter)
+        }
+    }
+
+    fn state_ptr(&mut self) -> *mut c_void {
+        &mut *self.state as *mut MeterState as *mut c_void
+    }
+}
+
+impl Drop for VolMeter {
+    fn drop(&mut self) {
+        unsafe {
+            ll::obs_volmeter_remove_callback(
+                self.meter,
+                Some(volmeter_callback_handler),
+                self.state_ptr(),
+            );
+            ll::obs_volmeter_detach_source(self.meter);
+            ll::obs_volmeter_destroy(self.meter);
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::{
+    backend::LevelMeter,
+    hl::{AudioLevels, Source, VolMeter},
+};
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+/// Collects levels from a source's volume meter between polls.  Peaks are
+/// held until the next `take` so short spikes are not lost when polling less
+/// often than the audio thread reports.
+pub struct LevelWatch {
+    name: String,
+    levels: Arc<Mutex<Option<AudioLevels>>>,
+    _meter: VolMeter,
+}
+
+impl LevelWatch {
+    pub fn new(source: &Source) -> Result<LevelWatch> {
+        let levels: Arc<Mutex<Option<AudioLevels>>> = Arc::new(Mutex::new(None));
+        let meter_levels = levels.clone();
+        let meter = VolMeter::new(source, move |new: &AudioLevels| {
+            let mut levels = meter_levels.lock().unwrap();
+            match levels.as_mut() {
+                Some(held) => {
+                    held.magnitude = new.magnitude.clone();
+                    hold_peaks(&mut held.peak, &new.peak);
+                    hold_peaks(&mut held.input_peak, &new.input_peak);
+                }
+                None => *levels = Some(new.clone()),
+            }
+        })?;
+
+        Ok(LevelWatch {
+            name: source.get_name()?,
+            levels,
+            _meter: meter,
+        })
+    }
+}
+
+impl LevelMeter for LevelWatch {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn take(&self) -> Option<AudioLevels> {
+        self.levels.lock().unwrap().take()
+    }
+}
+
+fn hold_peaks(held: &mut Vec<f32>, new: &[f32]) {
+    if held.len() != new.len() {
+        *held = new.to_vec();
+        return;
+    }
+    for (held, new) in held.iter_mut().zip(new) {
+        *held = held.max(*new);
+    }
+}
@@ -1,55 +1,431 @@
+// Settings that only the libobs backend reads would all be dead code otherwise.
+#![cfg_attr(not(feature = "libobs"), allow(dead_code))]
+// The backends return tonic's `Status` as is, boxing it buys nothing.
+#![allow(clippy::result_large_err)]
+
 use anyhow::{anyhow, Result};
-use obs;
+use backend::{
+    CurrentScene, FilterInfo, ItemOrder, LevelMeter, MockBackend, ObsBackend, OutputInfo,
+    RecordingInfo, SceneItemInfo, SourceInfo, Transform,
+};
+use config::{Config, Opt};
+use events::{Event, EventFeed};
 use rpc::{
     obs_server::{Obs, ObsServer},
-    GetSourceStatusReply, GetSourceStatusRequest, SetSourceVolumeRequest, SetStreamReply,
-    SetStreamRequest, SourceStatus, TestReply, TestRequest,
-};
-use std::{
-    ffi::{c_void, CStr, CString},
-    fs,
-    ptr::{null, null_mut},
+    set_scene_item_order_request::Order,
+    AddFilterRequest, AddOutputReply, AddOutputRequest, CreateSourceReply, CreateSourceRequest,
+    FilterReply, FilterStatus, GetCurrentSceneReply, GetCurrentSceneRequest,
+    GetRecordingStatusReply, GetRecordingStatusRequest, GetSourceSettingsReply,
+    GetSourceSettingsRequest, GetSourceStatusReply, GetSourceStatusRequest, GetStreamStatusReply,
+    GetStreamStatusRequest, ListEncodersReply, ListEncodersRequest, ListFiltersReply,
+    ListFiltersRequest, ListModulesReply, ListModulesRequest, ListOutputsReply, ListOutputsRequest,
+    ListSceneItemsReply, ListSceneItemsRequest, MoveFilterRequest, RemoveFilterRequest,
+    RemoveOutputReply, RemoveOutputRequest, RemoveSourceReply, RemoveSourceRequest,
+    SaveReplayReply, SaveReplayRequest, SceneItemStatus, SetCurrentSceneReply,
+    SetCurrentSceneRequest, SetFilterEnabledRequest, SetFilterSettingsRequest,
+    SetSceneItemCropRequest, SetSceneItemOrderRequest, SetSceneItemReply,
+    SetSceneItemTransformRequest, SetSceneItemVisibleRequest, SetSourceAudioReply,
+    SetSourceBalanceRequest, SetSourceMixersRequest, SetSourceMonitoringRequest,
+    SetSourceMutedRequest, SetSourceSettingsReply, SetSourceSettingsRequest,
+    SetSourceSyncOffsetRequest, SetSourceVolumeRequest, SetStreamDestinationReply,
+    SetStreamDestinationRequest, SetStreamReply, SetStreamRequest, SourceAudioLevels, SourceStatus,
+    StartRecordingReply, StartRecordingRequest, StartReplayBufferReply, StartReplayBufferRequest,
+    StartStreamReply, StartStreamRequest, StopReason, StopRecordingReply, StopRecordingRequest,
+    StopReplayBufferReply, StopReplayBufferRequest, StopStreamReply, StopStreamRequest,
+    StreamState, TestReply, TestRequest, WatchAudioLevelsRequest, WatchEventsRequest,
 };
+use std::{net::SocketAddr, time::Duration};
+use structopt::StructOpt;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{transport::Server, Request, Response, Status};
+use worker::{Worker, WorkerError};
 
+#[cfg(feature = "libobs")]
+use {
+    anyhow::Context,
+    backend::HlBackend,
+    hl::{Session, SourceEvents},
+    std::fs,
+};
+
+mod backend;
+mod config;
+mod events;
+#[cfg(feature = "libobs")]
 mod hl;
+#[cfg(feature = "libobs")]
+mod levels;
+#[cfg(feature = "libobs")]
+mod reconnect;
+mod serialize;
+mod types;
+mod worker;
 
-use hl::{Array, Data, Session, SessionSettings, Source};
+use types::{
+    BoundsType, Crop, EncoderKind, EncoderType, MediaState, ModuleStatus, MonitoringType,
+    OrderMovement, OutputEvent, OutputState, SourceEvent, StopCode, StreamDestination, Vec2,
+    MAIN_OUTPUT,
+};
 
-#[derive(Default)]
-pub struct ThisServer {}
+const REPLAY_SAVE_TIMEOUT: Duration = Duration::from_secs(30);
 
-fn set_url(source: &str, url: &str) -> Result<()> {
-    unsafe {
-        let source = obs::obs_get_source_by_name(cstr!(source));
-        let mut settings = Data::from_raw(obs::obs_source_get_settings(source));
+pub struct ThisServer<B> {
+    worker: Worker<B>,
+    event_feed: EventFeed,
+}
 
-        let mut playlist = Array::new()?;
-        let mut item = Data::new()?;
-        item.set_bool("hidden", false)?;
-        item.set_bool("selected", false)?;
-        item.set_string("value", url)?;
-        playlist.push_back(item);
+impl<B: ObsBackend> ThisServer<B> {
+    fn new(worker: Worker<B>, event_feed: EventFeed) -> ThisServer<B> {
+        ThisServer { worker, event_feed }
+    }
 
-        settings.set_array("playlist", playlist)?;
-        obs::obs_source_update(source, settings.as_mut_ptr());
-        // let str = obs::obs_data_get_json(settings.as_mut_ptr());
-        // let json = CStr::from_ptr(str);
-        //println!("settings: {:?}", json);
+    /// Runs `f` on the OBS thread.
+    async fn obs<R, F>(&self, f: F) -> Result<R, Status>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut B) -> Result<R, Status> + Send + 'static,
+    {
+        self.worker.run(f).await.map_err(|e| match e {
+            WorkerError::Stopped => Status::unavailable(e.to_string()),
+            WorkerError::Panicked => Status::internal(e.to_string()),
+        })?
+    }
+}
 
-        obs::obs_source_release(source);
+fn module_status_to_rpc(status: &ModuleStatus) -> rpc::ModuleStatus {
+    let (bin_path, data_path) = match &status.info {
+        Some(info) => (info.bin_path.clone(), info.data_path.clone()),
+        None => (String::new(), String::new()),
+    };
+    rpc::ModuleStatus {
+        name: status.name.clone(),
+        bin_path,
+        data_path,
+        required: status.required,
+        loaded: status.loaded,
+        error: status.error.clone().unwrap_or_default(),
     }
-    Ok(())
 }
 
-fn set_volume(source: &str, volume: f32) -> Result<()> {
-    let mut source = Source::by_name(source)?;
-    source.set_volume(volume);
-    Ok(())
+fn encoder_type_to_rpc(encoder: &EncoderType) -> rpc::EncoderType {
+    let kind = match encoder.kind {
+        EncoderKind::Audio => rpc::EncoderKind::Audio,
+        EncoderKind::Video => rpc::EncoderKind::Video,
+    };
+    rpc::EncoderType {
+        id: encoder.id.clone(),
+        name: encoder.name.clone(),
+        codec: encoder.codec.clone(),
+        kind: kind as i32,
+    }
+}
+
+fn stream_destination_to_rpc(destination: &StreamDestination) -> rpc::StreamDestination {
+    rpc::StreamDestination {
+        service: destination.service.clone().unwrap_or_default(),
+        server: destination.server.clone(),
+        key: destination.redacted_key(),
+    }
+}
+
+fn stream_destination_from_rpc(destination: rpc::StreamDestination) -> StreamDestination {
+    StreamDestination {
+        service: Some(destination.service).filter(|service| !service.is_empty()),
+        server: destination.server,
+        key: destination.key,
+    }
+}
+
+fn output_state_to_rpc(state: OutputState) -> StreamState {
+    match state {
+        OutputState::Stopped => StreamState::Stopped,
+        OutputState::Active => StreamState::Active,
+        OutputState::Reconnecting => StreamState::Reconnecting,
+    }
+}
+
+fn stop_reason_to_rpc(code: StopCode) -> StopReason {
+    match code {
+        StopCode::Success => StopReason::None,
+        StopCode::BadPath => StopReason::BadPath,
+        StopCode::ConnectFailed => StopReason::ConnectFailed,
+        StopCode::InvalidStream => StopReason::InvalidStream,
+        StopCode::Error => StopReason::Error,
+        StopCode::Disconnected => StopReason::Disconnected,
+        StopCode::Unsupported => StopReason::Unsupported,
+        StopCode::NoSpace => StopReason::NoSpace,
+        StopCode::EncodeError => StopReason::EncodeError,
+        StopCode::Unknown(_) => StopReason::Unknown,
+    }
+}
+
+fn output_event_to_rpc(name: &str, event: OutputEvent) -> rpc::OutputChanged {
+    let (state, stop_reason, retry_in_sec) = match event {
+        OutputEvent::Started => (StreamState::Active, StopReason::None, 0),
+        OutputEvent::Stopped(code) => (StreamState::Stopped, stop_reason_to_rpc(code), 0),
+        OutputEvent::Reconnecting { timeout_sec } => {
+            (StreamState::Reconnecting, StopReason::None, timeout_sec)
+        }
+    };
+    rpc::OutputChanged {
+        output: name.into(),
+        state: state as i32,
+        stop_reason: stop_reason as i32,
+        retry_in_sec,
+    }
+}
+
+fn media_state_to_rpc(state: MediaState) -> rpc::MediaState {
+    match state {
+        MediaState::Playing => rpc::MediaState::Playing,
+        MediaState::Paused => rpc::MediaState::Paused,
+        MediaState::Stopped => rpc::MediaState::Stopped,
+        MediaState::Ended => rpc::MediaState::Ended,
+    }
+}
+
+fn event_to_rpc(event: Event) -> rpc::Event {
+    let event = match event {
+        Event::Source(SourceEvent::Created { source, kind }) => {
+            rpc::event::Event::SourceCreated(rpc::SourceCreated { source, kind })
+        }
+        Event::Source(SourceEvent::Removed { source }) => {
+            rpc::event::Event::SourceRemoved(rpc::SourceRemoved { source })
+        }
+        Event::Source(SourceEvent::Renamed {
+            source,
+            previous_name,
+        }) => rpc::event::Event::SourceRenamed(rpc::SourceRenamed {
+            source,
+            previous_name,
+        }),
+        Event::Source(SourceEvent::VolumeChanged { source, volume }) => {
+            rpc::event::Event::VolumeChanged(rpc::VolumeChanged { source, volume })
+        }
+        Event::Source(SourceEvent::MuteChanged { source, muted }) => {
+            rpc::event::Event::MuteChanged(rpc::MuteChanged { source, muted })
+        }
+        Event::Source(SourceEvent::SceneSwitched { scene }) => {
+            rpc::event::Event::SceneSwitched(rpc::SceneSwitched { scene })
+        }
+        Event::Source(SourceEvent::MediaStateChanged { source, state }) => {
+            rpc::event::Event::MediaStateChanged(rpc::MediaStateChanged {
+                source,
+                state: media_state_to_rpc(state) as i32,
+            })
+        }
+        Event::Output { name, event } => {
+            rpc::event::Event::OutputChanged(output_event_to_rpc(&name, event))
+        }
+    };
+    rpc::Event { event: Some(event) }
+}
+
+fn source_info_to_rpc(source: SourceInfo) -> SourceStatus {
+    SourceStatus {
+        name: source.name,
+        has_video: source.has_video,
+        has_audio: source.has_audio,
+        is_composite: source.is_composite,
+        volume: source.volume,
+        muted: source.muted,
+        balance: source.balance,
+        sync_offset_ns: source.sync_offset_ns,
+        mixers: source.mixers,
+        monitoring_type: monitoring_type_to_rpc(source.monitoring_type) as i32,
+    }
+}
+
+fn output_status_to_rpc(output: OutputInfo) -> GetStreamStatusReply {
+    let status = output.status;
+    GetStreamStatusReply {
+        state: output_state_to_rpc(status.state) as i32,
+        uptime_ms: status.uptime.as_millis() as u64,
+        total_bytes: status.total_bytes,
+        total_frames: status.total_frames,
+        dropped_frames: status.dropped_frames,
+        congestion: status.congestion,
+        destination: Some(stream_destination_to_rpc(&output.destination)),
+        output: output.name,
+        last_error: status.last_error.unwrap_or_default(),
+    }
+}
+
+fn recording_status_to_rpc(recording: RecordingInfo) -> GetRecordingStatusReply {
+    let status = recording.status;
+    GetRecordingStatusReply {
+        state: output_state_to_rpc(status.state) as i32,
+        uptime_ms: status.uptime.as_millis() as u64,
+        total_bytes: status.total_bytes,
+        total_frames: status.total_frames,
+        dropped_frames: status.dropped_frames,
+        path: recording
+            .path
+            .map(|path| path.display().to_string())
+            .unwrap_or_default(),
+        last_error: status.last_error.unwrap_or_default(),
+    }
+}
+
+fn output_name(name: &str) -> &str {
+    if name.is_empty() {
+        MAIN_OUTPUT
+    } else {
+        name
+    }
+}
+
+const DEFAULT_LEVELS_INTERVAL: Duration = Duration::from_millis(100);
+const MIN_LEVELS_INTERVAL: Duration = Duration::from_millis(10);
+
+fn levels_to_rpc(watch: &dyn LevelMeter) -> SourceAudioLevels {
+    let levels = watch.take().unwrap_or_default();
+    SourceAudioLevels {
+        source: watch.name().into(),
+        magnitude: levels.magnitude,
+        peak: levels.peak,
+        input_peak: levels.input_peak,
+    }
+}
+
+fn monitoring_type_to_rpc(monitoring_type: MonitoringType) -> rpc::MonitoringType {
+    match monitoring_type {
+        MonitoringType::None => rpc::MonitoringType::None,
+        MonitoringType::MonitorOnly => rpc::MonitoringType::MonitorOnly,
+        MonitoringType::MonitorAndOutput => rpc::MonitoringType::MonitorAndOutput,
+    }
+}
+
+fn monitoring_type_from_rpc(monitoring_type: i32) -> std::result::Result<MonitoringType, Status> {
+    match rpc::MonitoringType::from_i32(monitoring_type) {
+        Some(rpc::MonitoringType::None) => Ok(MonitoringType::None),
+        Some(rpc::MonitoringType::MonitorOnly) => Ok(MonitoringType::MonitorOnly),
+        Some(rpc::MonitoringType::MonitorAndOutput) => Ok(MonitoringType::MonitorAndOutput),
+        None => Err(Status::invalid_argument(format!(
+            "unknown monitoring type {}",
+            monitoring_type
+        ))),
+    }
+}
+
+fn vec2_to_rpc(v: Vec2) -> rpc::Vec2 {
+    rpc::Vec2 { x: v.x, y: v.y }
+}
+
+fn vec2_from_rpc(v: rpc::Vec2) -> Vec2 {
+    Vec2 { x: v.x, y: v.y }
+}
+
+fn bounds_type_to_rpc(bounds_type: BoundsType) -> rpc::BoundsType {
+    match bounds_type {
+        BoundsType::None => rpc::BoundsType::None,
+        BoundsType::Stretch => rpc::BoundsType::Stretch,
+        BoundsType::ScaleInner => rpc::BoundsType::ScaleInner,
+        BoundsType::ScaleOuter => rpc::BoundsType::ScaleOuter,
+        BoundsType::ScaleToWidth => rpc::BoundsType::ScaleToWidth,
+        BoundsType::ScaleToHeight => rpc::BoundsType::ScaleToHeight,
+        BoundsType::MaxOnly => rpc::BoundsType::MaxOnly,
+    }
+}
+
+fn bounds_type_from_rpc(bounds_type: i32) -> std::result::Result<BoundsType, Status> {
+    match rpc::BoundsType::from_i32(bounds_type) {
+        Some(rpc::BoundsType::None) => Ok(BoundsType::None),
+        Some(rpc::BoundsType::Stretch) => Ok(BoundsType::Stretch),
+        Some(rpc::BoundsType::ScaleInner) => Ok(BoundsType::ScaleInner),
+        Some(rpc::BoundsType::ScaleOuter) => Ok(BoundsType::ScaleOuter),
+        Some(rpc::BoundsType::ScaleToWidth) => Ok(BoundsType::ScaleToWidth),
+        Some(rpc::BoundsType::ScaleToHeight) => Ok(BoundsType::ScaleToHeight),
+        Some(rpc::BoundsType::MaxOnly) => Ok(BoundsType::MaxOnly),
+        None => Err(Status::invalid_argument(format!(
+            "unknown bounds type {}",
+            bounds_type
+        ))),
+    }
+}
+
+fn order_movement_from_rpc(movement: i32) -> std::result::Result<OrderMovement, Status> {
+    match rpc::OrderMovement::from_i32(movement) {
+        Some(rpc::OrderMovement::Up) => Ok(OrderMovement::Up),
+        Some(rpc::OrderMovement::Down) => Ok(OrderMovement::Down),
+        Some(rpc::OrderMovement::Top) => Ok(OrderMovement::Top),
+        Some(rpc::OrderMovement::Bottom) => Ok(OrderMovement::Bottom),
+        None => Err(Status::invalid_argument(format!(
+            "unknown order movement {}",
+            movement
+        ))),
+    }
+}
+
+fn scene_item_status(item: SceneItemInfo, order: usize) -> SceneItemStatus {
+    let crop = item.crop;
+    SceneItemStatus {
+        id: item.id,
+        source: item.source,
+        visible: item.visible,
+        order: order as i32,
+        position: Some(vec2_to_rpc(item.position)),
+        scale: Some(vec2_to_rpc(item.scale)),
+        rotation: Some(rpc::Rotation {
+            degrees: item.rotation,
+        }),
+        bounds: Some(rpc::Bounds {
+            r#type: bounds_type_to_rpc(item.bounds.bounds_type) as i32,
+            size: Some(vec2_to_rpc(item.bounds.size)),
+            alignment: item.bounds.alignment,
+        }),
+        crop: Some(rpc::Crop {
+            left: crop.left,
+            top: crop.top,
+            right: crop.right,
+            bottom: crop.bottom,
+        }),
+    }
+}
+
+// Everything is validated before the backend touches the item so a bad
+// request doesn't leave it half updated.
+fn transform_from_rpc(
+    req: &SetSceneItemTransformRequest,
+) -> std::result::Result<Transform, Status> {
+    let mut transform = Transform {
+        position: req.position.clone().map(vec2_from_rpc),
+        scale: req.scale.clone().map(vec2_from_rpc),
+        rotation: req.rotation.as_ref().map(|rotation| rotation.degrees),
+        ..Default::default()
+    };
+    if let Some(bounds) = &req.bounds {
+        transform.bounds_type = Some(bounds_type_from_rpc(bounds.r#type)?);
+        transform.bounds_size = bounds.size.clone().map(vec2_from_rpc);
+        transform.bounds_alignment = Some(bounds.alignment);
+    }
+    Ok(transform)
+}
+
+fn filter_info_to_rpc(filter: FilterInfo) -> FilterStatus {
+    FilterStatus {
+        name: filter.name,
+        kind: filter.kind,
+        enabled: filter.enabled,
+        settings_json: filter.settings_json,
+    }
+}
+
+fn current_scene_to_rpc(current: CurrentScene) -> GetCurrentSceneReply {
+    GetCurrentSceneReply {
+        scene: current.scene,
+        transition: current.transition,
+        duration_ms: current.duration_ms,
+        transitions: current.transitions,
+    }
 }
 
 #[tonic::async_trait]
-impl Obs for ThisServer {
+impl<B: ObsBackend> Obs for ThisServer<B> {
+    type WatchAudioLevelsStream = ReceiverStream<Result<rpc::AudioLevels, Status>>;
+    type WatchEventsStream = ReceiverStream<Result<rpc::Event, Status>>;
+
     async fn test(
         &self,
         request: Request<TestRequest>,
@@ -72,11 +448,12 @@ impl Obs for ThisServer {
         println!("Got set stream request from {:?}", request.remote_addr());
 
         let req = request.into_inner();
-        let source = req.source;
-        let url = req.url;
-        set_url(&source, &url).map_err(|e| Status::new(tonic::Code::Unknown, format!("{}", e)))?;
-
-        let reply = SetStreamReply {};
+        let reply = self
+            .obs(move |obs| {
+                obs.set_playlist(&req.source, &[req.url])?;
+                Ok(SetStreamReply {})
+            })
+            .await?;
         Ok(Response::new(reply))
     }
 
@@ -87,12 +464,12 @@ impl Obs for ThisServer {
         println!("Got set stream request from {:?}", request.remote_addr());
 
         let req = request.into_inner();
-        let source = req.source;
-        let volume = req.volume;
-        set_volume(&source, volume)
-            .map_err(|e| Status::new(tonic::Code::Unknown, format!("{}", e)))?;
-
-        let reply = SetStreamReply {};
+        let reply = self
+            .obs(move |obs| {
+                obs.set_volume(&req.source, req.volume)?;
+                Ok(SetStreamReply {})
+            })
+            .await?;
         Ok(Response::new(reply))
     }
 
@@ -102,52 +479,1401 @@ impl Obs for ThisServer {
     ) -> Result<tonic::Response<GetSourceStatusReply>, tonic::Status> {
         println!("Got get stream status from {:?}", request.remote_addr());
 
-        let mut sources = Vec::new();
-        Source::for_each(|source: &Source| {
-            sources.push(SourceStatus {
-                name: source.get_name().unwrap_or("".into()),
-                has_video: source.has_video(),
-                has_audio: source.has_audio(),
-                is_composite: source.is_composite(),
-                volume: source.get_volume(),
-            });
+        let reply = self
+            .obs(move |obs| {
+                let sources = obs.sources().into_iter().map(source_info_to_rpc).collect();
+                Ok(GetSourceStatusReply { sources })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn get_source_settings(
+        &self,
+        request: tonic::Request<GetSourceSettingsRequest>,
+    ) -> Result<tonic::Response<GetSourceSettingsReply>, tonic::Status> {
+        println!("Got get source settings from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let json = obs.source_settings(&req.source, req.pretty)?;
+                Ok(GetSourceSettingsReply { json })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_settings(
+        &self,
+        request: tonic::Request<SetSourceSettingsRequest>,
+    ) -> Result<tonic::Response<SetSourceSettingsReply>, tonic::Status> {
+        println!("Got set source settings from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_source_settings(&req.source, &req.json, req.merge)?;
+                Ok(SetSourceSettingsReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn list_scene_items(
+        &self,
+        request: tonic::Request<ListSceneItemsRequest>,
+    ) -> Result<tonic::Response<ListSceneItemsReply>, tonic::Status> {
+        println!("Got list scene items from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let items = obs
+                    .scene_items(&req.scene)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(order, item)| scene_item_status(item, order))
+                    .collect();
+                Ok(ListSceneItemsReply { items })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_scene_item_transform(
+        &self,
+        request: tonic::Request<SetSceneItemTransformRequest>,
+    ) -> Result<tonic::Response<SetSceneItemReply>, tonic::Status> {
+        println!(
+            "Got set scene item transform from {:?}",
+            request.remote_addr()
+        );
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let transform = transform_from_rpc(&req)?;
+                obs.set_item_transform(&req.scene, &req.item, &transform)?;
+                Ok(SetSceneItemReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_scene_item_crop(
+        &self,
+        request: tonic::Request<SetSceneItemCropRequest>,
+    ) -> Result<tonic::Response<SetSceneItemReply>, tonic::Status> {
+        println!("Got set scene item crop from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let crop = req
+                    .crop
+                    .ok_or_else(|| Status::invalid_argument("crop is required"))?;
+                let crop = Crop {
+                    left: crop.left,
+                    top: crop.top,
+                    right: crop.right,
+                    bottom: crop.bottom,
+                };
+                obs.set_item_crop(&req.scene, &req.item, crop)?;
+                Ok(SetSceneItemReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_scene_item_visible(
+        &self,
+        request: tonic::Request<SetSceneItemVisibleRequest>,
+    ) -> Result<tonic::Response<SetSceneItemReply>, tonic::Status> {
+        println!(
+            "Got set scene item visible from {:?}",
+            request.remote_addr()
+        );
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_item_visible(&req.scene, &req.item, req.visible)?;
+                Ok(SetSceneItemReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_scene_item_order(
+        &self,
+        request: tonic::Request<SetSceneItemOrderRequest>,
+    ) -> Result<tonic::Response<SetSceneItemReply>, tonic::Status> {
+        println!("Got set scene item order from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let order = match req.order {
+                    Some(Order::Movement(movement)) => {
+                        ItemOrder::Move(order_movement_from_rpc(movement)?)
+                    }
+                    Some(Order::Position(position)) => ItemOrder::Position(position),
+                    None => return Err(Status::invalid_argument("order is required")),
+                };
+                obs.set_item_order(&req.scene, &req.item, order)?;
+                Ok(SetSceneItemReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn create_source(
+        &self,
+        request: tonic::Request<CreateSourceRequest>,
+    ) -> Result<tonic::Response<CreateSourceReply>, tonic::Status> {
+        println!("Got create source from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let settings = Some(req.settings_json.as_str()).filter(|json| !json.is_empty());
+                let scene_item_id = obs.create_source(&req.id, &req.name, settings, &req.scene)?;
+                Ok(CreateSourceReply { scene_item_id })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn remove_source(
+        &self,
+        request: tonic::Request<RemoveSourceRequest>,
+    ) -> Result<tonic::Response<RemoveSourceReply>, tonic::Status> {
+        println!("Got remove source from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.remove_source(&req.name)?;
+                Ok(RemoveSourceReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_muted(
+        &self,
+        request: tonic::Request<SetSourceMutedRequest>,
+    ) -> Result<tonic::Response<SetSourceAudioReply>, tonic::Status> {
+        println!("Got set source muted from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_muted(&req.source, req.muted)?;
+                Ok(SetSourceAudioReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_balance(
+        &self,
+        request: tonic::Request<SetSourceBalanceRequest>,
+    ) -> Result<tonic::Response<SetSourceAudioReply>, tonic::Status> {
+        println!("Got set source balance from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                if !(0.0..=1.0).contains(&req.balance) {
+                    return Err(Status::invalid_argument(format!(
+                        "balance {} is outside of 0.0..1.0",
+                        req.balance
+                    )));
+                }
+                obs.set_balance(&req.source, req.balance)?;
+                Ok(SetSourceAudioReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_sync_offset(
+        &self,
+        request: tonic::Request<SetSourceSyncOffsetRequest>,
+    ) -> Result<tonic::Response<SetSourceAudioReply>, tonic::Status> {
+        println!(
+            "Got set source sync offset from {:?}",
+            request.remote_addr()
+        );
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_sync_offset(&req.source, req.sync_offset_ns)?;
+                Ok(SetSourceAudioReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_mixers(
+        &self,
+        request: tonic::Request<SetSourceMixersRequest>,
+    ) -> Result<tonic::Response<SetSourceAudioReply>, tonic::Status> {
+        println!("Got set source mixers from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_mixers(&req.source, req.mixers)?;
+                Ok(SetSourceAudioReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_source_monitoring(
+        &self,
+        request: tonic::Request<SetSourceMonitoringRequest>,
+    ) -> Result<tonic::Response<SetSourceAudioReply>, tonic::Status> {
+        println!("Got set source monitoring from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let monitoring_type = monitoring_type_from_rpc(req.monitoring_type)?;
+                obs.set_monitoring_type(&req.source, monitoring_type)?;
+                Ok(SetSourceAudioReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn watch_audio_levels(
+        &self,
+        request: tonic::Request<WatchAudioLevelsRequest>,
+    ) -> Result<tonic::Response<Self::WatchAudioLevelsStream>, tonic::Status> {
+        println!("Got watch audio levels from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let sources = req.sources;
+        let watches = self.obs(move |obs| obs.watch_levels(&sources)).await?;
+        let interval = match req.interval_ms {
+            0 => DEFAULT_LEVELS_INTERVAL,
+            ms => Duration::from_millis(ms.into()).max(MIN_LEVELS_INTERVAL),
+        };
+
+        let (tx, rx) = mpsc::channel(4);
+        let worker = self.worker.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let levels = rpc::AudioLevels {
+                    sources: watches
+                        .iter()
+                        .map(|watch| levels_to_rpc(watch.as_ref()))
+                        .collect(),
+                };
+                if tx.send(Ok(levels)).await.is_err() {
+                    break;
+                }
+            }
+            // The client went away.  The meters are detached on the OBS
+            // thread.
+            let _ = worker.run(move |_| drop(watches)).await;
         });
-        let reply = GetSourceStatusReply { sources };
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn list_filters(
+        &self,
+        request: tonic::Request<ListFiltersRequest>,
+    ) -> Result<tonic::Response<ListFiltersReply>, tonic::Status> {
+        println!("Got list filters from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let filters = obs
+                    .filters(&req.source)?
+                    .into_iter()
+                    .map(filter_info_to_rpc)
+                    .collect();
+                Ok(ListFiltersReply { filters })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn add_filter(
+        &self,
+        request: tonic::Request<AddFilterRequest>,
+    ) -> Result<tonic::Response<FilterReply>, tonic::Status> {
+        println!("Got add filter from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let settings = Some(req.settings_json.as_str()).filter(|json| !json.is_empty());
+                obs.add_filter(&req.source, &req.kind, &req.name, settings)?;
+                Ok(FilterReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn remove_filter(
+        &self,
+        request: tonic::Request<RemoveFilterRequest>,
+    ) -> Result<tonic::Response<FilterReply>, tonic::Status> {
+        println!("Got remove filter from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.remove_filter(&req.source, &req.filter)?;
+                Ok(FilterReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_filter_enabled(
+        &self,
+        request: tonic::Request<SetFilterEnabledRequest>,
+    ) -> Result<tonic::Response<FilterReply>, tonic::Status> {
+        println!("Got set filter enabled from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_filter_enabled(&req.source, &req.filter, req.enabled)?;
+                Ok(FilterReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn move_filter(
+        &self,
+        request: tonic::Request<MoveFilterRequest>,
+    ) -> Result<tonic::Response<FilterReply>, tonic::Status> {
+        println!("Got move filter from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let movement = order_movement_from_rpc(req.movement)?;
+                obs.move_filter(&req.source, &req.filter, movement)?;
+                Ok(FilterReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_filter_settings(
+        &self,
+        request: tonic::Request<SetFilterSettingsRequest>,
+    ) -> Result<tonic::Response<FilterReply>, tonic::Status> {
+        println!("Got set filter settings from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                obs.set_filter_settings(&req.source, &req.filter, &req.json, req.merge)?;
+                Ok(FilterReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_current_scene(
+        &self,
+        request: tonic::Request<SetCurrentSceneRequest>,
+    ) -> Result<tonic::Response<SetCurrentSceneReply>, tonic::Status> {
+        println!("Got set current scene from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let transition = Some(req.transition.as_str()).filter(|name| !name.is_empty());
+                let duration_ms = Some(req.duration_ms).filter(|&ms| ms != 0);
+                obs.set_current_scene(&req.scene, transition, duration_ms)?;
+                Ok(SetCurrentSceneReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn get_current_scene(
+        &self,
+        request: tonic::Request<GetCurrentSceneRequest>,
+    ) -> Result<tonic::Response<GetCurrentSceneReply>, tonic::Status> {
+        println!("Got get current scene from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| obs.current_scene().map(current_scene_to_rpc))
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn list_modules(
+        &self,
+        request: tonic::Request<ListModulesRequest>,
+    ) -> Result<tonic::Response<ListModulesReply>, tonic::Status> {
+        println!("Got list modules from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                Ok(ListModulesReply {
+                    modules: obs.modules().iter().map(module_status_to_rpc).collect(),
+                })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn list_encoders(
+        &self,
+        request: tonic::Request<ListEncodersRequest>,
+    ) -> Result<tonic::Response<ListEncodersReply>, tonic::Status> {
+        println!("Got list encoders from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                let encoders = obs.encoders()?;
+                Ok(ListEncodersReply {
+                    encoders: encoders.iter().map(encoder_type_to_rpc).collect(),
+                })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn start_stream(
+        &self,
+        request: tonic::Request<StartStreamRequest>,
+    ) -> Result<tonic::Response<StartStreamReply>, tonic::Status> {
+        println!("Got start stream from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let name = output_name(&req.output);
+                if obs.output(name)?.status.state != OutputState::Stopped {
+                    return Err(Status::failed_precondition(format!(
+                        "output {:?} is already active",
+                        name
+                    )));
+                }
+                obs.start_output(name)?;
+                Ok(StartStreamReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn stop_stream(
+        &self,
+        request: tonic::Request<StopStreamRequest>,
+    ) -> Result<tonic::Response<StopStreamReply>, tonic::Status> {
+        println!("Got stop stream from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let name = output_name(&req.output);
+                if obs.output(name)?.status.state == OutputState::Stopped {
+                    return Err(Status::failed_precondition(format!(
+                        "output {:?} is not active",
+                        name
+                    )));
+                }
+                obs.stop_output(name)?;
+                Ok(StopStreamReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn get_stream_status(
+        &self,
+        request: tonic::Request<GetStreamStatusRequest>,
+    ) -> Result<tonic::Response<GetStreamStatusReply>, tonic::Status> {
+        println!("Got get stream status from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| Ok(output_status_to_rpc(obs.output(output_name(&req.output))?)))
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn set_stream_destination(
+        &self,
+        request: tonic::Request<SetStreamDestinationRequest>,
+    ) -> Result<tonic::Response<SetStreamDestinationReply>, tonic::Status> {
+        println!(
+            "Got set stream destination from {:?}",
+            request.remote_addr()
+        );
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                let destination = req
+                    .destination
+                    .map(stream_destination_from_rpc)
+                    .ok_or_else(|| Status::invalid_argument("missing destination"))?;
+                destination
+                    .validate()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+                obs.set_output_destination(output_name(&req.output), destination)?;
+                Ok(SetStreamDestinationReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn add_output(
+        &self,
+        request: tonic::Request<AddOutputRequest>,
+    ) -> Result<tonic::Response<AddOutputReply>, tonic::Status> {
+        println!("Got add output from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                if req.name.is_empty() {
+                    return Err(Status::invalid_argument("missing output name"));
+                }
+                let destination = req
+                    .destination
+                    .map(stream_destination_from_rpc)
+                    .ok_or_else(|| Status::invalid_argument("missing destination"))?;
+                destination
+                    .validate()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+                obs.add_output(&req.name, destination)?;
+                if req.start {
+                    obs.start_output(&req.name)?;
+                }
+                Ok(AddOutputReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn remove_output(
+        &self,
+        request: tonic::Request<RemoveOutputRequest>,
+    ) -> Result<tonic::Response<RemoveOutputReply>, tonic::Status> {
+        println!("Got remove output from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                if output_name(&req.name) == MAIN_OUTPUT {
+                    return Err(Status::failed_precondition(
+                        "the main output can't be removed",
+                    ));
+                }
+                obs.remove_output(&req.name)?;
+                Ok(RemoveOutputReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn start_recording(
+        &self,
+        request: tonic::Request<StartRecordingRequest>,
+    ) -> Result<tonic::Response<StartRecordingReply>, tonic::Status> {
+        println!("Got start recording from {:?}", request.remote_addr());
+
+        let req = request.into_inner();
+        let reply = self
+            .obs(move |obs| {
+                if obs.recording().status.state != OutputState::Stopped {
+                    return Err(Status::failed_precondition("recording is already active"));
+                }
+                let name = if req.name.is_empty() {
+                    "recording"
+                } else {
+                    &req.name
+                };
+                let path = obs.start_recording(name)?;
+                Ok(StartRecordingReply {
+                    path: path.display().to_string(),
+                })
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn stop_recording(
+        &self,
+        request: tonic::Request<StopRecordingRequest>,
+    ) -> Result<tonic::Response<StopRecordingReply>, tonic::Status> {
+        println!("Got stop recording from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                if obs.recording().status.state == OutputState::Stopped {
+                    return Err(Status::failed_precondition("recording is not active"));
+                }
+                obs.stop_recording()?;
+                Ok(StopRecordingReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn get_recording_status(
+        &self,
+        request: tonic::Request<GetRecordingStatusRequest>,
+    ) -> Result<tonic::Response<GetRecordingStatusReply>, tonic::Status> {
+        println!("Got get recording status from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| Ok(recording_status_to_rpc(obs.recording())))
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn start_replay_buffer(
+        &self,
+        request: tonic::Request<StartReplayBufferRequest>,
+    ) -> Result<tonic::Response<StartReplayBufferReply>, tonic::Status> {
+        println!("Got start replay buffer from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                if obs.replay_buffer_status().state != OutputState::Stopped {
+                    return Err(Status::failed_precondition(
+                        "replay buffer is already active",
+                    ));
+                }
+                obs.start_replay_buffer()?;
+                Ok(StartReplayBufferReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn stop_replay_buffer(
+        &self,
+        request: tonic::Request<StopReplayBufferRequest>,
+    ) -> Result<tonic::Response<StopReplayBufferReply>, tonic::Status> {
+        println!("Got stop replay buffer from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                if obs.replay_buffer_status().state == OutputState::Stopped {
+                    return Err(Status::failed_precondition("replay buffer is not active"));
+                }
+                obs.stop_replay_buffer()?;
+                Ok(StopReplayBufferReply {})
+            })
+            .await?;
+        Ok(Response::new(reply))
+    }
+
+    async fn save_replay(
+        &self,
+        request: tonic::Request<SaveReplayRequest>,
+    ) -> Result<tonic::Response<SaveReplayReply>, tonic::Status> {
+        println!("Got save replay from {:?}", request.remote_addr());
+
+        let saved = self
+            .obs(|obs| {
+                if obs.replay_buffer_status().state == OutputState::Stopped {
+                    return Err(Status::failed_precondition("replay buffer is not active"));
+                }
+                obs.save_replay()
+            })
+            .await?;
+
+        // The buffer is written by the muxer in the background.  libobs has
+        // no signal for a failed write, hence the timeout.
+        let path = match tokio::time::timeout(REPLAY_SAVE_TIMEOUT, saved).await {
+            Ok(Ok(Ok(path))) => path,
+            Ok(Ok(Err(e))) => return Err(Status::aborted(e)),
+            Ok(Err(_)) => return Err(Status::unavailable("replay buffer was shut down")),
+            Err(_) => return Err(Status::deadline_exceeded("replay was not saved in time")),
+        };
+
+        let reply = SaveReplayReply {
+            path: path.display().to_string(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    async fn watch_events(
+        &self,
+        request: tonic::Request<WatchEventsRequest>,
+    ) -> Result<tonic::Response<Self::WatchEventsStream>, tonic::Status> {
+        println!("Got watch events from {:?}", request.remote_addr());
+
+        let mut events = self.event_feed.subscribe();
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        println!("watch events client missed {} events", missed);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if tx.send(Ok(event_to_rpc(event))).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn list_outputs(
+        &self,
+        request: tonic::Request<ListOutputsRequest>,
+    ) -> Result<tonic::Response<ListOutputsReply>, tonic::Status> {
+        println!("Got list outputs from {:?}", request.remote_addr());
+
+        let reply = self
+            .obs(move |obs| {
+                Ok(ListOutputsReply {
+                    outputs: obs
+                        .outputs()
+                        .into_iter()
+                        .map(output_status_to_rpc)
+                        .collect(),
+                })
+            })
+            .await?;
         Ok(Response::new(reply))
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let settings = SessionSettings {
-        base_width: 1280,
-        base_height: 720,
-        output_width: 1280,
-        output_height: 720,
-        fps: 30,
-        audio_samples_per_sec: 48000,
-        stream_url: "rtmp://localhost:1935/live/".into(),
-        stream_key: "key".into(),
-    };
-    let mut session = Session::new(&settings)?;
+    let config = Config::load(Opt::from_args())?;
 
-    let json_str = fs::read_to_string("obs.json")?;
-    session.load_config_json(&json_str)?;
+    let (event_feed, _) = broadcast::channel(events::FEED_CAPACITY);
+    if config.mock {
+        let feed = event_feed.clone();
+        let worker = Worker::spawn(move || Ok(MockBackend::new(feed))).await?;
+        return serve(worker, event_feed, config.listen_addr).await;
+    }
 
-    session.start()?;
+    serve_libobs(config, event_feed).await
+}
 
-    let addr = "[::1]:50051".parse().unwrap();
+#[cfg(feature = "libobs")]
+async fn serve_libobs(config: Config, event_feed: EventFeed) -> Result<()> {
+    let (output_events, output_events_receiver) = mpsc::unbounded_channel();
 
-    println!("GreeterServer listening on {}", addr);
+    let settings = config.session_settings();
+    let scene_collection = config.scene_collection.clone();
+    let streams: Vec<_> = config
+        .streams()
+        .map(|(name, stream)| (name.to_string(), stream.destination(), stream.autostart))
+        .collect();
+    let replay_autostart = config.replay_buffer.autostart;
+    let feed = event_feed.clone();
+    let events = output_events.clone();
+    let worker = Worker::spawn(move || {
+        let mut session = Session::new(&settings)?;
 
-    loop {
-        let server = ThisServer::default();
-        Server::builder()
-            .add_service(ObsServer::new(server))
-            .serve(addr)
-            .await?;
-        println!("loop");
-    }
+        let json_str = fs::read_to_string(&scene_collection)
+            .with_context(|| format!("failed to read {:?}", scene_collection))?;
+        session.load_config_json(&json_str)?;
+
+        let source_events = SourceEvents::watch(move |event| {
+            // Fails if no client is watching.
+            let _ = feed.send(Event::Source(event));
+        })?;
+
+        for (name, destination, autostart) in streams {
+            if !session.has_output(&name) {
+                session.add_output(&name, destination)?;
+            }
+            reconnect::watch_output(session.output_mut(&name)?, &events)?;
+            if autostart {
+                session.output_mut(&name)?.start()?;
+            }
+        }
+        if replay_autostart {
+            session.replay_buffer_mut().start()?;
+        }
+
+        Ok(HlBackend::new(session, source_events, events))
+    })
+    .await?;
+
+    tokio::spawn(reconnect::run(
+        worker.clone(),
+        config.reconnect_policy(),
+        output_events,
+        output_events_receiver,
+        event_feed.clone(),
+    ));
 
+    serve(worker, event_feed, config.listen_addr).await
+}
+
+#[cfg(not(feature = "libobs"))]
+async fn serve_libobs(_config: Config, _event_feed: EventFeed) -> Result<()> {
+    Err(anyhow!(
+        "built without the libobs feature, only --mock is supported"
+    ))
+}
+
+async fn serve<B: ObsBackend>(
+    worker: Worker<B>,
+    event_feed: EventFeed,
+    addr: SocketAddr,
+) -> Result<()> {
+    println!("listening on {}", addr);
+
+    let server = ThisServer::new(worker, event_feed);
+    Server::builder()
+        .add_service(ObsServer::new(server))
+        .serve(addr)
+        .await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rpc::obs_client::ObsClient;
+    use tokio::net::TcpListener;
+    use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
+    use tonic::{transport::Channel, Code};
+
+    // Serves a fresh mock backend on a free local port.
+    async fn connect() -> ObsClient<Channel> {
+        let (event_feed, _) = broadcast::channel(events::FEED_CAPACITY);
+        let feed = event_feed.clone();
+        let worker = Worker::spawn(move || Ok(MockBackend::new(feed)))
+            .await
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(ObsServer::new(ThisServer::new(worker, event_feed)))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        ObsClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap()
+    }
+
+    async fn create_source(client: &mut ObsClient<Channel>, id: &str, name: &str, scene: &str) {
+        client
+            .create_source(CreateSourceRequest {
+                id: id.into(),
+                name: name.into(),
+                settings_json: "".into(),
+                scene: scene.into(),
+            })
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn sources() {
+        let mut client = connect().await;
+        create_source(&mut client, "scene", "Scene", "").await;
+        let reply = client
+            .create_source(CreateSourceRequest {
+                id: "color_source".into(),
+                name: "Color".into(),
+                settings_json: r#"{"color": 1}"#.into(),
+                scene: "Scene".into(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_ne!(reply.scene_item_id, 0);
+
+        let err = client
+            .create_source(CreateSourceRequest {
+                id: "color_source".into(),
+                name: "Color".into(),
+                settings_json: "".into(),
+                scene: "".into(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::AlreadyExists);
+
+        client
+            .set_source_settings(SetSourceSettingsRequest {
+                source: "Color".into(),
+                json: r#"{"width": 64}"#.into(),
+                merge: true,
+            })
+            .await
+            .unwrap();
+        let settings = client
+            .get_source_settings(GetSourceSettingsRequest {
+                source: "Color".into(),
+                pretty: false,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(settings.json, r#"{"color":1,"width":64}"#);
+
+        let err = client
+            .get_source_settings(GetSourceSettingsRequest {
+                source: "Missing".into(),
+                pretty: false,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        client
+            .remove_source(RemoveSourceRequest {
+                name: "Color".into(),
+            })
+            .await
+            .unwrap();
+        let items = client
+            .list_scene_items(ListSceneItemsRequest {
+                scene: "Scene".into(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(items.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn settings_must_be_an_object() {
+        let mut client = connect().await;
+        create_source(&mut client, "color_source", "Color", "").await;
+        client
+            .set_source_settings(SetSourceSettingsRequest {
+                source: "Color".into(),
+                json: r#"{"color": 1}"#.into(),
+                merge: false,
+            })
+            .await
+            .unwrap();
+        for json in &["", "[1]", "3", "null", "{"] {
+            let err = client
+                .set_source_settings(SetSourceSettingsRequest {
+                    source: "Color".into(),
+                    json: json.to_string(),
+                    merge: false,
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(err.code(), Code::InvalidArgument, "json {:?}", json);
+        }
+        let settings = client
+            .get_source_settings(GetSourceSettingsRequest {
+                source: "Color".into(),
+                pretty: false,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(settings.json, r#"{"color":1}"#);
+    }
+
+    #[tokio::test]
+    async fn audio() {
+        let mut client = connect().await;
+        create_source(&mut client, "ffmpeg_source", "Music", "").await;
+        client
+            .set_source_volume(SetSourceVolumeRequest {
+                source: "Music".into(),
+                volume: 0.5,
+            })
+            .await
+            .unwrap();
+        client
+            .set_source_muted(SetSourceMutedRequest {
+                source: "Music".into(),
+                muted: true,
+            })
+            .await
+            .unwrap();
+        client
+            .set_source_monitoring(SetSourceMonitoringRequest {
+                source: "Music".into(),
+                monitoring_type: rpc::MonitoringType::MonitorOnly as i32,
+            })
+            .await
+            .unwrap();
+        let err = client
+            .set_source_balance(SetSourceBalanceRequest {
+                source: "Music".into(),
+                balance: 2.0,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let sources = client
+            .get_source_status(GetSourceStatusRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .sources;
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].volume, 0.5);
+        assert!(sources[0].muted);
+        assert_eq!(
+            sources[0].monitoring_type,
+            rpc::MonitoringType::MonitorOnly as i32
+        );
+
+        let mut levels = client
+            .watch_audio_levels(WatchAudioLevelsRequest {
+                sources: vec![],
+                interval_ms: 10,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        let levels = levels.next().await.unwrap().unwrap();
+        assert_eq!(levels.sources.len(), 1);
+        assert_eq!(levels.sources[0].source, "Music");
+        assert_eq!(levels.sources[0].peak, vec![f32::NEG_INFINITY; 2]);
+    }
+
+    #[tokio::test]
+    async fn scene_items() {
+        let mut client = connect().await;
+        create_source(&mut client, "scene", "Scene", "").await;
+        create_source(&mut client, "color_source", "Bottom", "Scene").await;
+        create_source(&mut client, "color_source", "Top", "Scene").await;
+
+        client
+            .set_scene_item_transform(SetSceneItemTransformRequest {
+                scene: "Scene".into(),
+                item: "Bottom".into(),
+                position: Some(rpc::Vec2 { x: 10.0, y: 20.0 }),
+                scale: None,
+                rotation: Some(rpc::Rotation { degrees: 90.0 }),
+                bounds: None,
+            })
+            .await
+            .unwrap();
+        client
+            .set_scene_item_visible(SetSceneItemVisibleRequest {
+                scene: "Scene".into(),
+                item: "Bottom".into(),
+                visible: false,
+            })
+            .await
+            .unwrap();
+        client
+            .set_scene_item_order(SetSceneItemOrderRequest {
+                scene: "Scene".into(),
+                item: "Bottom".into(),
+                order: Some(Order::Movement(rpc::OrderMovement::Top as i32)),
+            })
+            .await
+            .unwrap();
+
+        let items = client
+            .list_scene_items(ListSceneItemsRequest {
+                scene: "Scene".into(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .items;
+        let sources: Vec<_> = items.iter().map(|item| item.source.as_str()).collect();
+        assert_eq!(sources, ["Top", "Bottom"]);
+        let moved = &items[1];
+        assert_eq!(moved.order, 1);
+        assert!(!moved.visible);
+        assert_eq!(moved.position, Some(rpc::Vec2 { x: 10.0, y: 20.0 }));
+        assert_eq!(moved.scale, Some(rpc::Vec2 { x: 1.0, y: 1.0 }));
+        assert_eq!(moved.rotation, Some(rpc::Rotation { degrees: 90.0 }));
+
+        let err = client
+            .set_scene_item_visible(SetSceneItemVisibleRequest {
+                scene: "Scene".into(),
+                item: "Missing".into(),
+                visible: true,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        client
+            .set_current_scene(SetCurrentSceneRequest {
+                scene: "Scene".into(),
+                transition: "Cut".into(),
+                duration_ms: 0,
+            })
+            .await
+            .unwrap();
+        let current = client
+            .get_current_scene(GetCurrentSceneRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(current.scene, "Scene");
+        assert_eq!(current.transition, "Cut");
+
+        let err = client
+            .set_current_scene(SetCurrentSceneRequest {
+                scene: "Scene".into(),
+                transition: "Missing".into(),
+                duration_ms: 0,
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        client
+            .set_current_scene(SetCurrentSceneRequest {
+                scene: "Scene".into(),
+                transition: "".into(),
+                duration_ms: 0,
+            })
+            .await
+            .unwrap();
+        let current = client
+            .get_current_scene(GetCurrentSceneRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(current.transition, "Cut");
+    }
+
+    #[tokio::test]
+    async fn filters() {
+        let mut client = connect().await;
+        create_source(&mut client, "color_source", "Color", "").await;
+        for name in &["First", "Second"] {
+            client
+                .add_filter(AddFilterRequest {
+                    source: "Color".into(),
+                    kind: "crop_filter".into(),
+                    name: name.to_string(),
+                    settings_json: "".into(),
+                })
+                .await
+                .unwrap();
+        }
+        client
+            .set_filter_enabled(SetFilterEnabledRequest {
+                source: "Color".into(),
+                filter: "Second".into(),
+                enabled: false,
+            })
+            .await
+            .unwrap();
+        client
+            .set_filter_settings(SetFilterSettingsRequest {
+                source: "Color".into(),
+                filter: "Second".into(),
+                json: r#"{"left": 8}"#.into(),
+                merge: false,
+            })
+            .await
+            .unwrap();
+        client
+            .move_filter(MoveFilterRequest {
+                source: "Color".into(),
+                filter: "Second".into(),
+                movement: rpc::OrderMovement::Top as i32,
+            })
+            .await
+            .unwrap();
+        client
+            .remove_filter(RemoveFilterRequest {
+                source: "Color".into(),
+                filter: "First".into(),
+            })
+            .await
+            .unwrap();
+
+        let filters = client
+            .list_filters(ListFiltersRequest {
+                source: "Color".into(),
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .filters;
+        assert_eq!(
+            filters,
+            vec![FilterStatus {
+                name: "Second".into(),
+                kind: "crop_filter".into(),
+                enabled: false,
+                settings_json: r#"{"left":8}"#.into(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn outputs() {
+        let mut client = connect().await;
+        client
+            .add_output(AddOutputRequest {
+                name: "backup".into(),
+                destination: Some(rpc::StreamDestination {
+                    service: "".into(),
+                    server: "rtmp://example.com/live".into(),
+                    key: "0123456789abcdef".into(),
+                }),
+                start: true,
+            })
+            .await
+            .unwrap();
+        let status = client
+            .get_stream_status(GetStreamStatusRequest {
+                output: "backup".into(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.state, StreamState::Active as i32);
+        assert_eq!(status.destination.unwrap().key, "************cdef");
+
+        let err = client
+            .start_stream(StartStreamRequest {
+                output: "backup".into(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        client
+            .stop_stream(StopStreamRequest {
+                output: "backup".into(),
+            })
+            .await
+            .unwrap();
+
+        let err = client
+            .set_stream_destination(SetStreamDestinationRequest {
+                destination: Some(rpc::StreamDestination {
+                    service: "".into(),
+                    server: "http://example.com".into(),
+                    key: "".into(),
+                }),
+                output: "".into(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = client
+            .remove_output(RemoveOutputRequest { name: "".into() })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        client
+            .remove_output(RemoveOutputRequest {
+                name: "backup".into(),
+            })
+            .await
+            .unwrap();
+        let outputs = client
+            .list_outputs(ListOutputsRequest {})
+            .await
+            .unwrap()
+            .into_inner()
+            .outputs;
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].output, MAIN_OUTPUT);
+    }
+
+    #[tokio::test]
+    async fn recording_and_replay() {
+        let mut client = connect().await;
+        let path = client
+            .start_recording(StartRecordingRequest { name: "".into() })
+            .await
+            .unwrap()
+            .into_inner()
+            .path;
+        let status = client
+            .get_recording_status(GetRecordingStatusRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(status.state, StreamState::Active as i32);
+        assert_eq!(status.path, path);
+        client
+            .stop_recording(StopRecordingRequest {})
+            .await
+            .unwrap();
+        let err = client
+            .stop_recording(StopRecordingRequest {})
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+
+        let err = client.save_replay(SaveReplayRequest {}).await.unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+        client
+            .start_replay_buffer(StartReplayBufferRequest {})
+            .await
+            .unwrap();
+        let first = client
+            .save_replay(SaveReplayRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        let second = client
+            .save_replay(SaveReplayRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        assert_ne!(first.path, second.path);
+    }
+
+    #[tokio::test]
+    async fn events() {
+        let mut client = connect().await;
+        let mut events = client
+            .watch_events(WatchEventsRequest {})
+            .await
+            .unwrap()
+            .into_inner();
+        create_source(&mut client, "color_source", "Color", "").await;
+        client
+            .start_stream(StartStreamRequest { output: "".into() })
+            .await
+            .unwrap();
+
+        let event = events.next().await.unwrap().unwrap();
+        assert_eq!(
+            event.event,
+            Some(rpc::event::Event::SourceCreated(rpc::SourceCreated {
+                source: "Color".into(),
+                kind: "color_source".into(),
+            }))
+        );
+        let event = events.next().await.unwrap().unwrap();
+        match event.event {
+            Some(rpc::event::Event::OutputChanged(changed)) => {
+                assert_eq!(changed.output, MAIN_OUTPUT);
+                assert_eq!(changed.state, StreamState::Active as i32);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{
+    backend::HlBackend,
+    events::{Event, EventFeed},
+    hl::StreamOutput,
+    types::{OutputEvent, OutputState, ReconnectPolicy, StopCode},
+    worker::Worker,
+};
+use anyhow::Result;
+use std::{collections::HashMap, time::Duration};
+use tokio::sync::mpsc;
+
+/// Events of the stream outputs by output name.
+pub type OutputEvents = mpsc::UnboundedSender<(String, OutputEvent)>;
+
+/// Sends `output`'s events to `events`.
+pub fn watch_output(output: &mut StreamOutput, events: &OutputEvents) -> Result<()> {
+    let name = output.get_name().to_string();
+    let events = events.clone();
+    output.connect_events(move |event| {
+        // Only fails once the service is shutting down.
+        let _ = events.send((name.clone(), event));
+    })
+}
+
+/// Restarts outputs according to `policy` as their events come in and passes
+/// the events on to `feed`.  This is the only reconnecting there is, libobs'
+/// own reconnect is disabled for every stream output.
+pub async fn run(
+    worker: Worker<HlBackend>,
+    policy: ReconnectPolicy,
+    events: OutputEvents,
+    mut receiver: mpsc::UnboundedReceiver<(String, OutputEvent)>,
+    feed: EventFeed,
+) {
+    let publish = |name: &str, event: OutputEvent| {
+        // Fails if no client is watching.
+        let _ = feed.send(Event::Output {
+            name: name.into(),
+            event,
+        });
+    };
+    let mut retries: HashMap<String, u32> = HashMap::new();
+    while let Some((name, event)) = receiver.recv().await {
+        println!("output {}: {:?}", name, event);
+        publish(&name, event);
+        if let OutputEvent::Stopped(_) = event {
+            if restart(&worker, &events, &name).await {
+                retries.remove(&name);
+                continue;
+            }
+        }
+        match event {
+            OutputEvent::Started => {
+                retries.remove(&name);
+                set_reconnecting(&worker, &name, false).await;
+            }
+            OutputEvent::Stopped(code) if code.is_error() => {
+                let retry = retries.entry(name.clone()).or_insert(0);
+                match policy.delay(*retry) {
+                    Some(delay) => {
+                        *retry += 1;
+                        println!(
+                            "output {}: reconnecting in {:?}, attempt {} of {}",
+                            name, delay, retry, policy.max_retries
+                        );
+                        set_reconnecting(&worker, &name, true).await;
+                        publish(
+                            &name,
+                            OutputEvent::Reconnecting {
+                                timeout_sec: delay.as_secs() as i64,
+                            },
+                        );
+                        tokio::spawn(reconnect(worker.clone(), events.clone(), name, delay));
+                    }
+                    None => {
+                        println!("output {}: giving up after {} retries", name, retry);
+                        retries.remove(&name);
+                        set_reconnecting(&worker, &name, false).await;
+                    }
+                }
+            }
+            OutputEvent::Stopped(_) => {
+                retries.remove(&name);
+            }
+            OutputEvent::Reconnecting { .. } => {}
+        }
+    }
+}
+
+async fn set_reconnecting(worker: &Worker<HlBackend>, name: &str, reconnecting: bool) {
+    let name = name.to_string();
+    let _ = worker
+        .run(move |obs| {
+            // The output may have been removed in the meantime.
+            if let Ok(output) = obs.session.output_mut(&name) {
+                output.set_reconnecting(reconnecting);
+            }
+        })
+        .await;
+}
+
+/// Restarts `name` if it was stopped to change its destination.  Returns
+/// false if it wasn't.
+async fn restart(worker: &Worker<HlBackend>, events: &OutputEvents, name: &str) -> bool {
+    let name = name.to_string();
+    let events = events.clone();
+    worker
+        .run(move |obs| {
+            let output = match obs.session.output_mut(&name) {
+                Ok(output) => output,
+                Err(_) => return false,
+            };
+            match output.finish_restart() {
+                Ok(restarted) => restarted,
+                Err(e) => {
+                    println!("output {}: restart failed: {}", name, e);
+                    // Handled like any other failed start.
+                    let _ = events.send((name, OutputEvent::Stopped(StopCode::Error)));
+                    true
+                }
+            }
+        })
+        .await
+        .unwrap_or(false)
+}
+
+async fn reconnect(worker: Worker<HlBackend>, events: OutputEvents, name: String, delay: Duration) {
+    tokio::time::sleep(delay).await;
+
+    let _ = worker
+        .run(move |obs| {
+            let output = match obs.session.output_mut(&name) {
+                Ok(output) => output,
+                Err(_) => return,
+            };
+            // Stopped or restarted by a client while waiting.
+            if output.status().state != OutputState::Reconnecting {
+                return;
+            }
+            if let Err(e) = output.reconnect() {
+                println!("output {}: {}", name, e);
+                // There is no stop signal if the output didn't even start.
+                let _ = events.send((name, OutputEvent::Stopped(StopCode::Error)));
+            }
+        })
+        .await;
+}
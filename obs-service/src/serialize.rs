@@ -0,0 +1,707 @@
+//! Serde support for obs data.
+//!
+//! `obs_data_t` can only hold objects at the top level and `obs_data_array_t`
+//! can only hold objects as elements, so `to_data` expects a struct or map and
+//! `to_array` expects a sequence of structs or maps.  `None` values are left
+//! unset and read back as `None`.
+//!
+//! The storage is behind `DataStore` and `ArrayStore`, implemented by
+//! `hl::Data` and `hl::Array`, so none of this needs libobs.
+
+use serde::{
+    de::{
+        self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    },
+    forward_to_deserialize_any,
+    ser::{self, Impossible, Serialize},
+};
+use std::{convert::TryFrom, fmt, marker::PhantomData, vec};
+
+/// An obs data object.
+pub trait DataStore: Sized {
+    type Array: ArrayStore<Data = Self>;
+
+    fn create() -> anyhow::Result<Self>;
+    /// Sets `key`, or leaves it unset for `DataValue::Null`.
+    fn set(&mut self, key: &str, value: DataValue<Self>) -> anyhow::Result<()>;
+    /// Every item in the object.
+    fn values(&self) -> anyhow::Result<Vec<(String, DataValue<Self>)>>;
+}
+
+/// An obs data array.
+pub trait ArrayStore: Sized {
+    type Data: DataStore<Array = Self>;
+
+    fn create() -> anyhow::Result<Self>;
+    fn push(&mut self, data: Self::Data);
+    fn items(&self) -> anyhow::Result<Vec<Self::Data>>;
+}
+
+pub enum DataValue<D: DataStore> {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    String(String),
+    Object(D),
+    Array(D::Array),
+}
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(e: anyhow::Error) -> Self {
+        Error(e.to_string())
+    }
+}
+
+pub fn to_data<D: DataStore, T: Serialize + ?Sized>(value: &T) -> anyhow::Result<D> {
+    match value.serialize(ValueSerializer(PhantomData))? {
+        DataValue::Object(data) => Ok(data),
+        _ => Err(anyhow::anyhow!(
+            "only structs and maps can be stored in obs data"
+        )),
+    }
+}
+
+pub fn to_array<A: ArrayStore, T: Serialize + ?Sized>(value: &T) -> anyhow::Result<A> {
+    match value.serialize(ValueSerializer::<A::Data>(PhantomData))? {
+        DataValue::Array(array) => Ok(array),
+        _ => Err(anyhow::anyhow!(
+            "only sequences can be stored in obs arrays"
+        )),
+    }
+}
+
+pub fn from_data<D: DataStore, T: DeserializeOwned>(data: &D) -> anyhow::Result<T> {
+    Ok(T::deserialize(DataDeserializer::new(data)?)?)
+}
+
+pub fn from_array<A: ArrayStore, T: DeserializeOwned>(array: &A) -> anyhow::Result<T> {
+    Ok(T::deserialize(ArrayDeserializer::<A::Data> {
+        items: array.items()?.into_iter(),
+    })?)
+}
+
+fn unsupported(what: &str) -> Error {
+    Error(format!("{} can not be stored in obs data", what))
+}
+
+struct ValueSerializer<D>(PhantomData<D>);
+
+impl<D: DataStore> ser::Serializer for ValueSerializer<D> {
+    type Ok = DataValue<D>;
+    type Error = Error;
+
+    type SerializeSeq = ArraySerializer<D>;
+    type SerializeTuple = ArraySerializer<D>;
+    type SerializeTupleStruct = Impossible<DataValue<D>, Error>;
+    type SerializeTupleVariant = Impossible<DataValue<D>, Error>;
+    type SerializeMap = DataSerializer<D>;
+    type SerializeStruct = DataSerializer<D>;
+    type SerializeStructVariant = Impossible<DataValue<D>, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Int(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<DataValue<D>, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<DataValue<D>, Error> {
+        let v = i64::try_from(v).map_err(|_| Error(format!("{} does not fit in an i64", v)))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<DataValue<D>, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Double(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::String(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::String(v.into()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<DataValue<D>, Error> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<DataValue<D>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<DataValue<D>, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<DataValue<D>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<DataValue<D>, Error> {
+        Err(unsupported("newtype variants"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ArraySerializer<D>, Error> {
+        Ok(ArraySerializer {
+            array: D::Array::create()?,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer<D>, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(unsupported("tuple structs"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported("tuple variants"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<DataSerializer<D>, Error> {
+        Ok(DataSerializer {
+            data: D::create()?,
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<DataSerializer<D>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported("struct variants"))
+    }
+}
+
+struct ArraySerializer<D: DataStore> {
+    array: D::Array,
+}
+
+impl<D: DataStore> ser::SerializeSeq for ArraySerializer<D> {
+    type Ok = DataValue<D>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        match value.serialize(ValueSerializer(PhantomData))? {
+            DataValue::Object(data) => {
+                self.array.push(data);
+                Ok(())
+            }
+            _ => Err(Error("obs arrays can only hold structs and maps".into())),
+        }
+    }
+
+    fn end(self) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Array(self.array))
+    }
+}
+
+impl<D: DataStore> ser::SerializeTuple for ArraySerializer<D> {
+    type Ok = DataValue<D>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<DataValue<D>, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct DataSerializer<D> {
+    data: D,
+    key: Option<String>,
+}
+
+impl<D: DataStore> ser::SerializeMap for DataSerializer<D> {
+    type Ok = DataValue<D>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer::<D>(PhantomData))? {
+            DataValue::String(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(Error("obs data keys must be strings".into())),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("value serialized before key".into()))?;
+        Ok(self
+            .data
+            .set(&key, value.serialize(ValueSerializer(PhantomData))?)?)
+    }
+
+    fn end(self) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Object(self.data))
+    }
+}
+
+impl<D: DataStore> ser::SerializeStruct for DataSerializer<D> {
+    type Ok = DataValue<D>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        Ok(self
+            .data
+            .set(key, value.serialize(ValueSerializer(PhantomData))?)?)
+    }
+
+    fn end(self) -> Result<DataValue<D>, Error> {
+        Ok(DataValue::Object(self.data))
+    }
+}
+
+struct DataDeserializer<D: DataStore> {
+    items: Vec<(String, DataValue<D>)>,
+}
+
+impl<D: DataStore> DataDeserializer<D> {
+    fn new(data: &D) -> Result<DataDeserializer<D>, Error> {
+        Ok(DataDeserializer {
+            items: data.values()?,
+        })
+    }
+}
+
+impl<'de, D: DataStore> de::Deserializer<'de> for DataDeserializer<D> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(DataMapAccess {
+            items: self.items.into_iter(),
+            value: None,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct DataMapAccess<D: DataStore> {
+    items: vec::IntoIter<(String, DataValue<D>)>,
+    value: Option<DataValue<D>>,
+}
+
+impl<'de, D: DataStore> MapAccess<'de> for DataMapAccess<D> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.items.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(key.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error("value requested past the end of obs data".into()))?;
+        seed.deserialize(ValueDeserializer(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct ArrayDeserializer<D> {
+    items: vec::IntoIter<D>,
+}
+
+impl<'de, D: DataStore> de::Deserializer<'de> for ArrayDeserializer<D> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, D: DataStore> SeqAccess<'de> for ArrayDeserializer<D> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some(data) => seed.deserialize(DataDeserializer::new(&data)?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct ValueDeserializer<D: DataStore>(DataValue<D>);
+
+impl<'de, D: DataStore> de::Deserializer<'de> for ValueDeserializer<D> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            DataValue::Null => visitor.visit_unit(),
+            DataValue::Bool(v) => visitor.visit_bool(v),
+            DataValue::Int(v) => visitor.visit_i64(v),
+            DataValue::Double(v) => visitor.visit_f64(v),
+            DataValue::String(v) => visitor.visit_string(v),
+            DataValue::Object(data) => DataDeserializer::new(&data)?.deserialize_any(visitor),
+            DataValue::Array(array) => ArrayDeserializer {
+                items: array.items()?.into_iter(),
+            }
+            .deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            DataValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            DataValue::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            _ => Err(Error(
+                "only unit enum variants can be read from obs data".into(),
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::PlaylistItem;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+
+    // A scene collection saved by obs.
+    const SCENE_COLLECTION: &str = include_str!("../../obs.json");
+
+    // In-memory stand-in for obs data.
+    #[derive(Clone, Debug, Default)]
+    struct MemData(Vec<(String, MemValue)>);
+
+    #[derive(Clone, Debug, Default)]
+    struct MemArray(Vec<MemData>);
+
+    #[derive(Clone, Debug)]
+    enum MemValue {
+        Bool(bool),
+        Int(i64),
+        Double(f64),
+        String(String),
+        Object(MemData),
+        Array(MemArray),
+    }
+
+    impl DataStore for MemData {
+        type Array = MemArray;
+
+        fn create() -> anyhow::Result<MemData> {
+            Ok(MemData::default())
+        }
+
+        fn set(&mut self, key: &str, value: DataValue<MemData>) -> anyhow::Result<()> {
+            self.0.retain(|(name, _)| name != key);
+            let value = match value {
+                DataValue::Null => return Ok(()),
+                DataValue::Bool(v) => MemValue::Bool(v),
+                DataValue::Int(v) => MemValue::Int(v),
+                DataValue::Double(v) => MemValue::Double(v),
+                DataValue::String(v) => MemValue::String(v),
+                DataValue::Object(v) => MemValue::Object(v),
+                DataValue::Array(v) => MemValue::Array(v),
+            };
+            self.0.push((key.into(), value));
+            Ok(())
+        }
+
+        fn values(&self) -> anyhow::Result<Vec<(String, DataValue<MemData>)>> {
+            Ok(self
+                .0
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value.clone() {
+                        MemValue::Bool(v) => DataValue::Bool(v),
+                        MemValue::Int(v) => DataValue::Int(v),
+                        MemValue::Double(v) => DataValue::Double(v),
+                        MemValue::String(v) => DataValue::String(v),
+                        MemValue::Object(v) => DataValue::Object(v),
+                        MemValue::Array(v) => DataValue::Array(v),
+                    };
+                    (name.clone(), value)
+                })
+                .collect())
+        }
+    }
+
+    impl ArrayStore for MemArray {
+        type Data = MemData;
+
+        fn create() -> anyhow::Result<MemArray> {
+            Ok(MemArray::default())
+        }
+
+        fn push(&mut self, data: MemData) {
+            self.0.push(data);
+        }
+
+        fn items(&self) -> anyhow::Result<Vec<MemData>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn data(json: &str) -> MemData {
+        to_data(&serde_json::from_str::<Value>(json).unwrap()).unwrap()
+    }
+
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        let data: MemData = to_data(value).unwrap();
+        from_data(&data).unwrap()
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Vec2 {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct SceneItem {
+        id: i64,
+        name: String,
+        visible: bool,
+        pos: Vec2,
+        rot: f64,
+        align: u32,
+        // Not saved by this version of obs.
+        blend_method: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct SceneSettings {
+        id_counter: i64,
+        items: Vec<SceneItem>,
+    }
+
+    #[test]
+    fn scene_collection_round_trip() {
+        let collection: Value = serde_json::from_str(SCENE_COLLECTION).unwrap();
+        assert_eq!(round_trip(&collection), collection);
+    }
+
+    #[test]
+    fn scene_collection_to_struct() {
+        let collection = data(SCENE_COLLECTION);
+        let sources: Vec<Value> = match collection
+            .values()
+            .unwrap()
+            .into_iter()
+            .find(|(name, _)| name == "sources")
+        {
+            Some((_, DataValue::Array(sources))) => from_array(&sources).unwrap(),
+            _ => panic!("no sources in the scene collection"),
+        };
+        let scene = sources
+            .iter()
+            .find(|source| source["id"] == "scene")
+            .unwrap();
+        let settings: MemData = to_data(&scene["settings"]).unwrap();
+
+        let settings: SceneSettings = from_data(&settings).unwrap();
+        assert!(!settings.items.is_empty());
+        assert!(settings
+            .items
+            .iter()
+            .all(|item| item.blend_method.is_none()));
+        assert_eq!(round_trip(&settings), settings);
+        // None is left unset.
+        let data: MemData = to_data(&settings.items[0]).unwrap();
+        assert!(data.0.iter().all(|(name, _)| name != "blend_method"));
+    }
+
+    #[test]
+    fn playlist_item() {
+        let item = PlaylistItem::new("https://example.com/a.mp4");
+        assert_eq!(round_trip(&item), item);
+        let playlist = vec![PlaylistItem::new("a"), PlaylistItem::new("b")];
+        let array: MemArray = to_array(&playlist).unwrap();
+        assert_eq!(
+            from_array::<_, Vec<PlaylistItem>>(&array).unwrap(),
+            playlist
+        );
+    }
+
+    #[test]
+    fn missing_key() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Partial {
+            name: Option<String>,
+            count: i64,
+        }
+
+        assert_eq!(
+            from_data::<_, Partial>(&data(r#"{"count": 7}"#)).unwrap(),
+            Partial {
+                name: None,
+                count: 7
+            }
+        );
+        assert!(from_data::<_, Partial>(&data(r#"{"name": "x"}"#)).is_err());
+    }
+
+    #[test]
+    fn type_mismatch_is_an_error() {
+        let mismatch = data(r#"{"x": "left", "y": 1.0}"#);
+        assert!(from_data::<_, Vec2>(&mismatch).is_err());
+        let mismatch = data(r#"{"items": {"id": 1}, "id_counter": 1}"#);
+        assert!(from_data::<_, SceneSettings>(&mismatch).is_err());
+        assert!(to_data::<MemData, _>(&3).is_err());
+        assert!(to_array::<MemArray, _>(&[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! Plain data shared by the server, the backends and the libobs wrappers in
+//! `hl`.  Nothing in here touches libobs, so the server builds without it.
+use anyhow::{anyhow, Result};
+use std::{fmt, path::PathBuf, time::Duration};
+use tokio::sync::oneshot;
+
+/// Name of the output created from `SessionSettings::stream_destination`.
+pub const MAIN_OUTPUT: &str = "main";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonitoringType {
+    None,
+    MonitorOnly,
+    MonitorAndOutput,
+}
+
+pub struct VideoEncoderSettings {
+    pub id: String,
+    /// Bitrate in kbps.
+    pub bitrate: u32,
+    pub keyint_sec: u32,
+    pub rate_control: String,
+    pub preset: String,
+    pub profile: String,
+    pub tune: String,
+    pub x264opts: String,
+}
+
+pub struct AudioEncoderSettings {
+    pub id: String,
+    /// Bitrate in kbps.
+    pub bitrate: u32,
+}
+
+pub struct SessionSettings {
+    /// Path or name of the libobs graphics module.
+    pub graphics_module: String,
+    pub display: DisplaySettings,
+    pub modules: ModuleSettings,
+    pub base_width: u32,
+    pub base_height: u32,
+    pub output_width: u32,
+    pub output_height: u32,
+    pub fps: u32,
+    pub audio_samples_per_sec: u32,
+    pub stream_destination: StreamDestination,
+    pub video_encoder: VideoEncoderSettings,
+    pub audio_encoder: AudioEncoderSettings,
+    pub recording: RecordingSettings,
+    pub replay_buffer: ReplayBufferSettings,
+}
+
+/// How libobs' OpenGL renderer gets its context.  libobs has no surfaceless
+/// platform, both need an X display even though nothing is ever shown on it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Platform {
+    Glx,
+    Egl,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Platform::Glx => "glx",
+            Platform::Egl => "egl",
+        })
+    }
+}
+
+pub struct DisplaySettings {
+    pub platform: Platform,
+    /// X display name, e.g. ":0".  `$DISPLAY` is used if None.
+    pub display: Option<String>,
+    /// Starts a private Xvfb server if there is no display to use.
+    pub xvfb: bool,
+    /// Path or name of the Xvfb binary.
+    pub xvfb_path: String,
+}
+
+/// A directory searched for modules.  `data` may contain `%module%`, which
+/// is replaced by the module's name.
+#[derive(Clone, Debug)]
+pub struct ModulePath {
+    pub bin: String,
+    pub data: String,
+}
+
+pub struct ModuleSettings {
+    pub paths: Vec<ModulePath>,
+    /// Modules that must load for the session to start.
+    pub required: Vec<String>,
+    /// Modules that are loaded if present.  Failures are only logged.
+    pub optional: Vec<String>,
+    /// Also load every other module found in `paths`.
+    pub load_all: bool,
+}
+
+/// A module found in the module search paths.
+#[derive(Clone, Debug)]
+pub struct ModuleInfo {
+    pub name: String,
+    pub bin_path: String,
+    pub data_path: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ModuleStatus {
+    pub name: String,
+    /// None if the module wasn't found.
+    pub info: Option<ModuleInfo>,
+    pub required: bool,
+    pub loaded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EncoderKind {
+    Audio,
+    Video,
+}
+
+/// An encoder type registered by a module.
+#[derive(Clone, Debug)]
+pub struct EncoderType {
+    pub id: String,
+    pub name: String,
+    pub codec: String,
+    pub kind: EncoderKind,
+}
+
+/// Where a stream is sent.  Uses an `rtmp_common` service if `service` is
+/// set, e.g. "Twitch", and `rtmp_custom` otherwise.
+#[derive(Clone, PartialEq)]
+pub struct StreamDestination {
+    pub service: Option<String>,
+    pub server: String,
+    pub key: String,
+}
+
+impl StreamDestination {
+    pub fn validate(&self) -> Result<()> {
+        if self.service.is_some() {
+            if self.server.is_empty() {
+                return Err(anyhow!("stream server must not be empty"));
+            }
+        } else if !self.server.starts_with("rtmp://") && !self.server.starts_with("rtmps://") {
+            return Err(anyhow!(
+                "stream server must start with rtmp:// or rtmps://, got {:?}",
+                self.server
+            ));
+        }
+        Ok(())
+    }
+
+    /// The stream key with all but its last four characters hidden.  Short
+    /// keys are hidden completely.
+    pub fn redacted_key(&self) -> String {
+        let chars: Vec<char> = self.key.chars().collect();
+        if chars.len() < 12 {
+            return "*".repeat(chars.len());
+        }
+        let (hidden, shown) = chars.split_at(chars.len() - 4);
+        "*".repeat(hidden.len()) + &shown.iter().collect::<String>()
+    }
+}
+
+// Keeps the stream key out of logs.
+impl fmt::Debug for StreamDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamDestination")
+            .field("service", &self.service)
+            .field("server", &self.server)
+            .field("key", &self.redacted_key())
+            .finish()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputState {
+    Stopped,
+    Active,
+    Reconnecting,
+}
+
+#[derive(Clone, Debug)]
+pub struct OutputStatus {
+    pub state: OutputState,
+    /// Time since the output was started.  Zero when stopped.
+    pub uptime: Duration,
+    pub total_bytes: u64,
+    pub total_frames: i32,
+    pub dropped_frames: i32,
+    /// Between 0.0 and 1.0.
+    pub congestion: f32,
+    /// The error that last stopped the output or kept it from starting.
+    pub last_error: Option<String>,
+}
+
+/// Why an output stopped, from the `code` of its `stop` signal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopCode {
+    /// Stopped on request.
+    Success,
+    BadPath,
+    ConnectFailed,
+    InvalidStream,
+    Error,
+    Disconnected,
+    Unsupported,
+    NoSpace,
+    EncodeError,
+    Unknown(i64),
+}
+
+impl StopCode {
+    pub fn is_error(self) -> bool {
+        self != StopCode::Success
+    }
+}
+
+/// An output's `start` and `stop` signals, and the retries scheduled by
+/// `reconnect::run`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputEvent {
+    Started,
+    Stopped(StopCode),
+    /// Waiting to retry in `timeout_sec`.
+    Reconnecting {
+        timeout_sec: i64,
+    },
+}
+
+/// How stream outputs that stopped with an error are restarted.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    pub retry_delay: Duration,
+    pub max_retry_delay: Duration,
+    /// Zero disables reconnecting.
+    pub max_retries: u32,
+    /// Factor the delay grows by after each failed retry.
+    pub backoff: f32,
+}
+
+impl ReconnectPolicy {
+    /// The delay before retry number `retry`, counting from zero, or None
+    /// once the retries are used up.
+    pub fn delay(&self, retry: u32) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+        let delay = self.retry_delay.as_secs_f32() * self.backoff.powi(retry as i32);
+        // Clamped before converting, the delay may be infinite by now.
+        Some(Duration::from_secs_f32(
+            delay.min(self.max_retry_delay.as_secs_f32()),
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordingFormat {
+    Mkv,
+    Mp4,
+    Flv,
+}
+
+impl RecordingFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Mkv => "mkv",
+            RecordingFormat::Mp4 => "mp4",
+            RecordingFormat::Flv => "flv",
+        }
+    }
+}
+
+pub struct RecordingSettings {
+    pub directory: PathBuf,
+    /// File name without extension.  `{name}` is replaced by the name passed
+    /// to `Recording::start` and the date and time are filled in like obs
+    /// does, e.g. `%CCYY-%MM-%DD %hh-%mm-%ss`.
+    pub filename_format: String,
+    pub format: RecordingFormat,
+    /// Dedicated encoders.  The stream's encoders are shared if None.
+    pub encoders: Option<(VideoEncoderSettings, AudioEncoderSettings)>,
+}
+
+pub struct ReplayBufferSettings {
+    pub directory: PathBuf,
+    /// File name without extension, filled in like obs does, e.g.
+    /// `Replay %CCYY-%MM-%DD %hh-%mm-%ss`.
+    pub filename_format: String,
+    pub format: RecordingFormat,
+    /// How much is kept in the buffer.
+    pub max_time_sec: u32,
+    pub max_size_mb: u32,
+}
+
+/// Resolved with the path of a saved replay, or why it wasn't saved.
+pub type ReplaySaved = oneshot::Receiver<std::result::Result<PathBuf, String>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaState {
+    Playing,
+    Paused,
+    Stopped,
+    Ended,
+}
+
+/// A change to a source, from libobs's global and per-source signals.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceEvent {
+    Created {
+        source: String,
+        kind: String,
+    },
+    Removed {
+        source: String,
+    },
+    Renamed {
+        source: String,
+        previous_name: String,
+    },
+    VolumeChanged {
+        source: String,
+        volume: f32,
+    },
+    MuteChanged {
+        source: String,
+        muted: bool,
+    },
+    /// A transition to `scene` started.
+    SceneSwitched {
+        scene: String,
+    },
+    MediaStateChanged {
+        source: String,
+        state: MediaState,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Crop {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoundsType {
+    None,
+    Stretch,
+    ScaleInner,
+    ScaleOuter,
+    ScaleToWidth,
+    ScaleToHeight,
+    MaxOnly,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OrderMovement {
+    Up,
+    Down,
+    Top,
+    Bottom,
+}
+
+/// Per channel levels in dBFS.  Silence is reported as negative infinity.
+#[derive(Clone, Debug, Default)]
+pub struct AudioLevels {
+    pub magnitude: Vec<f32>,
+    pub peak: Vec<f32>,
+    pub input_peak: Vec<f32>,
+}
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    thread,
+};
+use tokio::sync::{mpsc, oneshot};
+
+// Commands waiting for the OBS thread before senders have to wait.
+const COMMAND_QUEUE: usize = 64;
+
+type Command<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// Why `Worker::run` didn't return a result.
+#[derive(Debug, PartialEq)]
+pub enum WorkerError {
+    /// The OBS thread is gone.
+    Stopped,
+    /// The command panicked.  The thread keeps running the other commands.
+    Panicked,
+}
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WorkerError::Stopped => "obs thread is not running",
+            WorkerError::Panicked => "obs command panicked",
+        })
+    }
+}
+
+impl std::error::Error for WorkerError {}
+
+/// Handle to the thread that owns the obs state `S`, e.g. the libobs
+/// session.  libobs is only called from that thread, one command at a time in
+/// the order they were sent.
+pub struct Worker<S> {
+    commands: mpsc::Sender<Command<S>>,
+}
+
+// Derived Clone would require `S: Clone`.
+impl<S> Clone for Worker<S> {
+    fn clone(&self) -> Self {
+        Worker {
+            commands: self.commands.clone(),
+        }
+    }
+}
+
+impl<S: 'static> Worker<S> {
+    /// Starts the OBS thread and creates its state with `init` on it.  Returns
+    /// once `init` is done.
+    pub async fn spawn<F>(init: F) -> Result<Worker<S>>
+    where
+        F: FnOnce() -> Result<S> + Send + 'static,
+    {
+        let (commands, mut receiver) = mpsc::channel::<Command<S>>(COMMAND_QUEUE);
+        let (ready, ready_receiver) = oneshot::channel();
+        thread::Builder::new().name("obs".into()).spawn(move || {
+            let mut state = match init() {
+                Ok(state) => state,
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready.send(Ok(()));
+            while let Some(command) = receiver.blocking_recv() {
+                command(&mut state);
+            }
+            // The state is dropped here, still on this thread.
+        })?;
+        ready_receiver
+            .await
+            .map_err(|_| anyhow!("obs thread exited during startup"))??;
+        Ok(Worker { commands })
+    }
+
+    /// Runs `f` on the OBS thread and returns its result.  `f` is skipped if
+    /// the returned future is dropped before the thread gets to it, e.g.
+    /// because the client cancelled the request.
+    ///
+    /// A panic in `f` is returned as `WorkerError::Panicked`.  Whatever `f`
+    /// changed before it panicked stays changed.
+    pub async fn run<R, F>(&self, f: F) -> Result<R, WorkerError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut S) -> R + Send + 'static,
+    {
+        let (reply, reply_receiver) = oneshot::channel();
+        let command: Command<S> = Box::new(move |state| {
+            if reply.is_closed() {
+                return;
+            }
+            let result = panic::catch_unwind(AssertUnwindSafe(|| f(state)));
+            let _ = reply.send(result.map_err(|_| WorkerError::Panicked));
+        });
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| WorkerError::Stopped)?;
+        reply_receiver.await.map_err(|_| WorkerError::Stopped)?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn runs_commands_in_order() {
+        let worker = Worker::spawn(|| Ok(Vec::new())).await.unwrap();
+        for i in 0..3 {
+            worker.run(move |state| state.push(i)).await.unwrap();
+        }
+        assert_eq!(worker.run(|state| state.clone()).await, Ok(vec![0, 1, 2]));
+    }
+
+    #[tokio::test]
+    async fn init_error() {
+        let error = Worker::<()>::spawn(|| Err(anyhow!("no display")))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "no display");
+    }
+
+    #[tokio::test]
+    async fn panic_only_fails_its_command() {
+        let worker = Worker::spawn(|| Ok(0)).await.unwrap();
+        let result = worker
+            .run(|state: &mut i32| {
+                *state += 1;
+                panic!("command failed");
+            })
+            .await;
+        assert_eq!(result, Err(WorkerError::Panicked));
+        assert_eq!(worker.run(|state| *state).await, Ok(1));
+    }
+}
@@ -1,14 +1,442 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use rpc::{
-    obs_client::ObsClient, GetSourceStatusRequest, SetSourceVolumeRequest, SetStreamRequest,
+    obs_client::ObsClient, set_scene_item_order_request::Order, AddFilterRequest, AddOutputRequest,
+    BoundsType, CreateSourceRequest, GetCurrentSceneRequest, GetRecordingStatusRequest,
+    GetSourceSettingsRequest, GetSourceStatusRequest, GetStreamStatusRequest, ListEncodersRequest,
+    ListFiltersRequest, ListModulesRequest, ListOutputsRequest, ListSceneItemsRequest,
+    MonitoringType, MoveFilterRequest, OrderMovement, RemoveFilterRequest, RemoveOutputRequest,
+    RemoveSourceRequest, SaveReplayRequest, SetCurrentSceneRequest, SetFilterEnabledRequest,
+    SetFilterSettingsRequest, SetSceneItemCropRequest, SetSceneItemOrderRequest,
+    SetSceneItemTransformRequest, SetSceneItemVisibleRequest, SetSourceBalanceRequest,
+    SetSourceMixersRequest, SetSourceMonitoringRequest, SetSourceMutedRequest,
+    SetSourceSettingsRequest, SetSourceSyncOffsetRequest, SetSourceVolumeRequest,
+    SetStreamDestinationRequest, SetStreamRequest, StartRecordingRequest, StartReplayBufferRequest,
+    StartStreamRequest, StopRecordingRequest, StopReplayBufferRequest, StopStreamRequest,
+    StreamDestination, WatchAudioLevelsRequest, WatchEventsRequest,
 };
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 enum Opt {
-    SetStream { source_name: String, url: String },
+    SetStream {
+        source_name: String,
+        url: String,
+    },
     SourceStatus {},
-    Volume { source_name: String, volume: f32 },
+    Volume {
+        source_name: String,
+        volume: f32,
+    },
+    Settings {
+        source_name: String,
+        #[structopt(long)]
+        compact: bool,
+    },
+    SetSettings {
+        source_name: String,
+        json: String,
+        /// Replace the source's settings instead of merging into them.
+        #[structopt(long)]
+        replace: bool,
+    },
+    SceneItems {
+        scene: String,
+    },
+    Transform {
+        scene: String,
+        item: String,
+        #[structopt(long, number_of_values = 2, value_names = &["x", "y"])]
+        position: Vec<f32>,
+        #[structopt(long, number_of_values = 2, value_names = &["x", "y"])]
+        scale: Vec<f32>,
+        /// Rotation in degrees.
+        #[structopt(long)]
+        rotation: Option<f32>,
+        /// One of none, stretch, scale-inner, scale-outer, scale-to-width,
+        /// scale-to-height or max-only.
+        #[structopt(long, parse(try_from_str = parse_bounds_type))]
+        bounds_type: Option<BoundsType>,
+        #[structopt(long, number_of_values = 2, value_names = &["width", "height"])]
+        bounds: Vec<f32>,
+        /// Bitmask of OBS_ALIGN_* flags used with --bounds-type.
+        #[structopt(long, default_value = "0")]
+        bounds_alignment: u32,
+    },
+    Crop {
+        scene: String,
+        item: String,
+        left: i32,
+        top: i32,
+        right: i32,
+        bottom: i32,
+    },
+    Show {
+        scene: String,
+        item: String,
+    },
+    Hide {
+        scene: String,
+        item: String,
+    },
+    /// Move an item up, down, to the top, to the bottom or to an absolute
+    /// position counted from the bottom of the scene.
+    Order {
+        scene: String,
+        item: String,
+        #[structopt(parse(try_from_str = parse_order))]
+        order: Order,
+    },
+    CreateSource {
+        /// Source type id, e.g. vlc_source.
+        id: String,
+        name: String,
+        /// Initial settings as JSON.
+        #[structopt(long)]
+        settings: Option<String>,
+        /// Add the new source to this scene.
+        #[structopt(long)]
+        scene: Option<String>,
+    },
+    RemoveSource {
+        name: String,
+    },
+    Mute {
+        source_name: String,
+    },
+    Unmute {
+        source_name: String,
+    },
+    /// Set stereo balance from 0.0 (left) to 1.0 (right).
+    Balance {
+        source_name: String,
+        balance: f32,
+    },
+    /// Set the audio sync offset in milliseconds.
+    Sync {
+        source_name: String,
+        #[structopt(allow_hyphen_values = true)]
+        offset_ms: i64,
+    },
+    /// Set the bitmask of audio mixer tracks the source is sent to.
+    Mixers {
+        source_name: String,
+        mixers: u32,
+    },
+    Filters {
+        source_name: String,
+    },
+    AddFilter {
+        source_name: String,
+        /// Filter type id, e.g. crop_filter.
+        kind: String,
+        name: String,
+        /// Initial settings as JSON.
+        #[structopt(long)]
+        settings: Option<String>,
+    },
+    RemoveFilter {
+        source_name: String,
+        name: String,
+    },
+    EnableFilter {
+        source_name: String,
+        name: String,
+    },
+    DisableFilter {
+        source_name: String,
+        name: String,
+    },
+    /// Move a filter up, down, to the top or to the bottom of the chain.
+    MoveFilter {
+        source_name: String,
+        name: String,
+        #[structopt(parse(try_from_str = parse_movement))]
+        movement: OrderMovement,
+    },
+    FilterSettings {
+        source_name: String,
+        name: String,
+        json: String,
+        /// Replace the filter's settings instead of merging into them.
+        #[structopt(long)]
+        replace: bool,
+    },
+    /// Switch output to a scene.
+    Scene {
+        scene: String,
+        /// Transition to use from now on, e.g. Cut or Fade.
+        #[structopt(long)]
+        transition: Option<String>,
+        /// Transition duration.  Defaults to the configured duration.
+        #[structopt(long)]
+        duration_ms: Option<u32>,
+    },
+    CurrentScene {},
+    /// Show live audio levels.  Meters all audio sources if none are given.
+    Meters {
+        sources: Vec<String>,
+        #[structopt(long, default_value = "100")]
+        interval_ms: u32,
+    },
+    /// Set audio monitoring to none, monitor-only or monitor-and-output.
+    Monitoring {
+        source_name: String,
+        #[structopt(parse(try_from_str = parse_monitoring_type))]
+        monitoring_type: MonitoringType,
+    },
+    /// List the libobs modules obs-service found and loaded.
+    Modules {},
+    /// List the encoder types that can be used in the obs-service config.
+    Encoders {},
+    StartStream {
+        /// Output to use.  Defaults to the main output.
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    StopStream {
+        /// Output to use.  Defaults to the main output.
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    StreamStatus {
+        /// Output to use.  Defaults to the main output.
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    /// Change where the stream is sent.  An active stream is restarted.
+    SetDestination {
+        server: String,
+        key: String,
+        /// rtmp_common service name, e.g. Twitch.  Omit for a custom server.
+        #[structopt(long)]
+        service: Option<String>,
+        /// Output to use.  Defaults to the main output.
+        #[structopt(long)]
+        output: Option<String>,
+    },
+    /// Add an output streaming the same video to another destination.
+    AddOutput {
+        name: String,
+        server: String,
+        key: String,
+        /// rtmp_common service name, e.g. Twitch.  Omit for a custom server.
+        #[structopt(long)]
+        service: Option<String>,
+        #[structopt(long)]
+        start: bool,
+    },
+    RemoveOutput {
+        name: String,
+    },
+    Outputs {},
+    /// Start recording to a new file.  The name is used in the file name.
+    StartRecording {
+        name: Option<String>,
+    },
+    StopRecording {},
+    RecordingStatus {},
+    Replay {
+        #[structopt(subcommand)]
+        cmd: ReplayCmd,
+    },
+    /// Print source, scene and output changes as they happen.
+    Events {},
+}
+
+#[derive(Debug, StructOpt)]
+enum ReplayCmd {
+    /// Start keeping the last seconds of video in memory.
+    Start {},
+    Stop {},
+    /// Write the replay buffer to a new file.
+    Save {},
+}
+
+fn parse_bounds_type(s: &str) -> Result<BoundsType> {
+    match s {
+        "none" => Ok(BoundsType::None),
+        "stretch" => Ok(BoundsType::Stretch),
+        "scale-inner" => Ok(BoundsType::ScaleInner),
+        "scale-outer" => Ok(BoundsType::ScaleOuter),
+        "scale-to-width" => Ok(BoundsType::ScaleToWidth),
+        "scale-to-height" => Ok(BoundsType::ScaleToHeight),
+        "max-only" => Ok(BoundsType::MaxOnly),
+        _ => Err(anyhow!("unknown bounds type {:?}", s)),
+    }
+}
+
+fn parse_monitoring_type(s: &str) -> Result<MonitoringType> {
+    match s {
+        "none" => Ok(MonitoringType::None),
+        "monitor-only" => Ok(MonitoringType::MonitorOnly),
+        "monitor-and-output" => Ok(MonitoringType::MonitorAndOutput),
+        _ => Err(anyhow!("unknown monitoring type {:?}", s)),
+    }
+}
+
+fn parse_movement(s: &str) -> Result<OrderMovement> {
+    match s {
+        "up" => Ok(OrderMovement::Up),
+        "down" => Ok(OrderMovement::Down),
+        "top" => Ok(OrderMovement::Top),
+        "bottom" => Ok(OrderMovement::Bottom),
+        _ => Err(anyhow!("movement must be up, down, top or bottom")),
+    }
+}
+
+fn parse_order(s: &str) -> Result<Order> {
+    match parse_movement(s) {
+        Ok(movement) => Ok(Order::Movement(movement as i32)),
+        Err(_) => Ok(Order::Position(s.parse().map_err(|_| {
+            anyhow!("order must be up, down, top, bottom or a position")
+        })?)),
+    }
+}
+
+fn vec2(values: &[f32]) -> Option<rpc::Vec2> {
+    match values {
+        [x, y] => Some(rpc::Vec2 { x: *x, y: *y }),
+        _ => None,
+    }
+}
+
+async fn set_visible(scene: String, item: String, visible: bool) -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    let request = tonic::Request::new(SetSceneItemVisibleRequest {
+        scene,
+        item,
+        visible,
+    });
+
+    let _response = client.set_scene_item_visible(request).await?;
+    Ok(())
+}
+
+async fn replay(cmd: ReplayCmd) -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    match cmd {
+        ReplayCmd::Start {} => {
+            let request = tonic::Request::new(StartReplayBufferRequest {});
+
+            let _response = client.start_replay_buffer(request).await?;
+        }
+        ReplayCmd::Stop {} => {
+            let request = tonic::Request::new(StopReplayBufferRequest {});
+
+            let _response = client.stop_replay_buffer(request).await?;
+        }
+        ReplayCmd::Save {} => {
+            let request = tonic::Request::new(SaveReplayRequest {});
+
+            let response = client.save_replay(request).await?;
+
+            println!("replay saved to {}", response.into_inner().path);
+        }
+    }
+    Ok(())
+}
+
+const METER_WIDTH: usize = 50;
+const METER_MIN_DB: f32 = -60.0;
+
+fn meter_bar(magnitude: f32, peak: f32) -> String {
+    let scale = |db: f32| {
+        let db = if db.is_finite() { db } else { METER_MIN_DB };
+        let fraction = (db.clamp(METER_MIN_DB, 0.0) - METER_MIN_DB) / -METER_MIN_DB;
+        (fraction * METER_WIDTH as f32).round() as usize
+    };
+    let filled = scale(magnitude);
+    let peak = scale(peak);
+    (0..METER_WIDTH)
+        .map(|i| {
+            if i < filled {
+                '#'
+            } else if i + 1 == peak {
+                '|'
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn format_db(db: f32) -> String {
+    if db.is_finite() {
+        format!("{:6.1}", db)
+    } else {
+        "  -inf".into()
+    }
+}
+
+async fn show_meters(sources: Vec<String>, interval_ms: u32) -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    let request = tonic::Request::new(WatchAudioLevelsRequest {
+        sources,
+        interval_ms,
+    });
+
+    let mut stream = client.watch_audio_levels(request).await?.into_inner();
+    while let Some(levels) = stream.message().await? {
+        // Clear the screen and home the cursor before each update.
+        let mut out = String::from("\x1b[2J\x1b[H");
+        for source in levels.sources {
+            out.push_str(&format!("{}\n", source.source));
+            if source.magnitude.is_empty() {
+                out.push_str("  (no audio)\n");
+            }
+            for (channel, (magnitude, peak)) in
+                source.magnitude.iter().zip(source.peak.iter()).enumerate()
+            {
+                out.push_str(&format!(
+                    "  {} [{}] {} dB\n",
+                    channel,
+                    meter_bar(*magnitude, *peak),
+                    format_db(*peak)
+                ));
+            }
+        }
+        print!("{}", out);
+    }
+    Ok(())
+}
+
+async fn watch_events() -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    let request = tonic::Request::new(WatchEventsRequest {});
+
+    let mut stream = client.watch_events(request).await?.into_inner();
+    while let Some(event) = stream.message().await? {
+        if let Some(event) = event.event {
+            println!("{:?}", event);
+        }
+    }
+    Ok(())
+}
+
+async fn set_filter_enabled(source: String, filter: String, enabled: bool) -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    let request = tonic::Request::new(SetFilterEnabledRequest {
+        source,
+        filter,
+        enabled,
+    });
+
+    let _response = client.set_filter_enabled(request).await?;
+    Ok(())
+}
+
+async fn set_muted(source: String, muted: bool) -> Result<()> {
+    let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+    let request = tonic::Request::new(SetSourceMutedRequest { source, muted });
+
+    let _response = client.set_source_muted(request).await?;
+    Ok(())
 }
 
 #[tokio::main]
@@ -48,6 +476,424 @@ async fn main() -> Result<()> {
 
             println!("{:#?}", response);
         }
+        Opt::Settings {
+            source_name,
+            compact,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(GetSourceSettingsRequest {
+                source: source_name,
+                pretty: !compact,
+            });
+
+            let response = client.get_source_settings(request).await?;
+
+            println!("{}", response.into_inner().json);
+        }
+        Opt::SetSettings {
+            source_name,
+            json,
+            replace,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSourceSettingsRequest {
+                source: source_name,
+                json,
+                merge: !replace,
+            });
+
+            let _response = client.set_source_settings(request).await?;
+        }
+        Opt::SceneItems { scene } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(ListSceneItemsRequest { scene });
+
+            let response = client.list_scene_items(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::Transform {
+            scene,
+            item,
+            position,
+            scale,
+            rotation,
+            bounds_type,
+            bounds,
+            bounds_alignment,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSceneItemTransformRequest {
+                scene,
+                item,
+                position: vec2(&position),
+                scale: vec2(&scale),
+                rotation: rotation.map(|degrees| rpc::Rotation { degrees }),
+                bounds: bounds_type.map(|bounds_type| rpc::Bounds {
+                    r#type: bounds_type as i32,
+                    size: vec2(&bounds),
+                    alignment: bounds_alignment,
+                }),
+            });
+
+            let _response = client.set_scene_item_transform(request).await?;
+        }
+        Opt::Crop {
+            scene,
+            item,
+            left,
+            top,
+            right,
+            bottom,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSceneItemCropRequest {
+                scene,
+                item,
+                crop: Some(rpc::Crop {
+                    left,
+                    top,
+                    right,
+                    bottom,
+                }),
+            });
+
+            let _response = client.set_scene_item_crop(request).await?;
+        }
+        Opt::Show { scene, item } => set_visible(scene, item, true).await?,
+        Opt::Hide { scene, item } => set_visible(scene, item, false).await?,
+        Opt::Order { scene, item, order } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSceneItemOrderRequest {
+                scene,
+                item,
+                order: Some(order),
+            });
+
+            let _response = client.set_scene_item_order(request).await?;
+        }
+        Opt::CreateSource {
+            id,
+            name,
+            settings,
+            scene,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(CreateSourceRequest {
+                id,
+                name,
+                settings_json: settings.unwrap_or_default(),
+                scene: scene.unwrap_or_default(),
+            });
+
+            let response = client.create_source(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::RemoveSource { name } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(RemoveSourceRequest { name });
+
+            let _response = client.remove_source(request).await?;
+        }
+        Opt::Filters { source_name } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(ListFiltersRequest {
+                source: source_name,
+            });
+
+            let response = client.list_filters(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::AddFilter {
+            source_name,
+            kind,
+            name,
+            settings,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(AddFilterRequest {
+                source: source_name,
+                kind,
+                name,
+                settings_json: settings.unwrap_or_default(),
+            });
+
+            let _response = client.add_filter(request).await?;
+        }
+        Opt::RemoveFilter { source_name, name } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(RemoveFilterRequest {
+                source: source_name,
+                filter: name,
+            });
+
+            let _response = client.remove_filter(request).await?;
+        }
+        Opt::EnableFilter { source_name, name } => {
+            set_filter_enabled(source_name, name, true).await?
+        }
+        Opt::DisableFilter { source_name, name } => {
+            set_filter_enabled(source_name, name, false).await?
+        }
+        Opt::MoveFilter {
+            source_name,
+            name,
+            movement,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(MoveFilterRequest {
+                source: source_name,
+                filter: name,
+                movement: movement as i32,
+            });
+
+            let _response = client.move_filter(request).await?;
+        }
+        Opt::FilterSettings {
+            source_name,
+            name,
+            json,
+            replace,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetFilterSettingsRequest {
+                source: source_name,
+                filter: name,
+                json,
+                merge: !replace,
+            });
+
+            let _response = client.set_filter_settings(request).await?;
+        }
+        Opt::Scene {
+            scene,
+            transition,
+            duration_ms,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetCurrentSceneRequest {
+                scene,
+                transition: transition.unwrap_or_default(),
+                duration_ms: duration_ms.unwrap_or_default(),
+            });
+
+            let _response = client.set_current_scene(request).await?;
+        }
+        Opt::CurrentScene {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(GetCurrentSceneRequest {});
+
+            let response = client.get_current_scene(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::Meters {
+            sources,
+            interval_ms,
+        } => show_meters(sources, interval_ms).await?,
+        Opt::Mute { source_name } => set_muted(source_name, true).await?,
+        Opt::Unmute { source_name } => set_muted(source_name, false).await?,
+        Opt::Balance {
+            source_name,
+            balance,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSourceBalanceRequest {
+                source: source_name,
+                balance,
+            });
+
+            let _response = client.set_source_balance(request).await?;
+        }
+        Opt::Sync {
+            source_name,
+            offset_ms,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSourceSyncOffsetRequest {
+                source: source_name,
+                sync_offset_ns: offset_ms * 1_000_000,
+            });
+
+            let _response = client.set_source_sync_offset(request).await?;
+        }
+        Opt::Mixers {
+            source_name,
+            mixers,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSourceMixersRequest {
+                source: source_name,
+                mixers,
+            });
+
+            let _response = client.set_source_mixers(request).await?;
+        }
+        Opt::Monitoring {
+            source_name,
+            monitoring_type,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetSourceMonitoringRequest {
+                source: source_name,
+                monitoring_type: monitoring_type as i32,
+            });
+
+            let _response = client.set_source_monitoring(request).await?;
+        }
+        Opt::Modules {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(ListModulesRequest {});
+
+            let response = client.list_modules(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::Encoders {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(ListEncodersRequest {});
+
+            let response = client.list_encoders(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::StartStream { output } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(StartStreamRequest {
+                output: output.unwrap_or_default(),
+            });
+
+            let _response = client.start_stream(request).await?;
+        }
+        Opt::StopStream { output } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(StopStreamRequest {
+                output: output.unwrap_or_default(),
+            });
+
+            let _response = client.stop_stream(request).await?;
+        }
+        Opt::StreamStatus { output } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(GetStreamStatusRequest {
+                output: output.unwrap_or_default(),
+            });
+
+            let response = client.get_stream_status(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::SetDestination {
+            server,
+            key,
+            service,
+            output,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(SetStreamDestinationRequest {
+                destination: Some(StreamDestination {
+                    service: service.unwrap_or_default(),
+                    server,
+                    key,
+                }),
+                output: output.unwrap_or_default(),
+            });
+
+            let _response = client.set_stream_destination(request).await?;
+        }
+        Opt::AddOutput {
+            name,
+            server,
+            key,
+            service,
+            start,
+        } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(AddOutputRequest {
+                name,
+                destination: Some(StreamDestination {
+                    service: service.unwrap_or_default(),
+                    server,
+                    key,
+                }),
+                start,
+            });
+
+            let _response = client.add_output(request).await?;
+        }
+        Opt::RemoveOutput { name } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(RemoveOutputRequest { name });
+
+            let _response = client.remove_output(request).await?;
+        }
+        Opt::Outputs {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(ListOutputsRequest {});
+
+            let response = client.list_outputs(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::StartRecording { name } => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(StartRecordingRequest {
+                name: name.unwrap_or_default(),
+            });
+
+            let response = client.start_recording(request).await?;
+
+            println!("recording to {}", response.into_inner().path);
+        }
+        Opt::StopRecording {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(StopRecordingRequest {});
+
+            let _response = client.stop_recording(request).await?;
+        }
+        Opt::RecordingStatus {} => {
+            let mut client = ObsClient::connect("http://[::1]:50051").await?;
+
+            let request = tonic::Request::new(GetRecordingStatusRequest {});
+
+            let response = client.get_recording_status(request).await?;
+
+            println!("{:#?}", response);
+        }
+        Opt::Replay { cmd } => replay(cmd).await?,
+        Opt::Events {} => watch_events().await?,
     }
 
     Ok(())